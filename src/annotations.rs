@@ -0,0 +1,378 @@
+//! Point and set tags/notes stored in a JSON sidecar file next to the data.
+//!
+//! Each directory may contain [SIDECAR_NAME] file with the annotation of the directory (set)
+//! and annotations of its points (by file name). Sidecars are read and written directly, so
+//! annotations are available only for local data (see [available]).
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use egui::Ui;
+use serde::{Deserialize, Serialize};
+
+/// Name of the sidecar file.
+pub const SIDECAR_NAME: &str = "annotations.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tag {
+    Good,
+    Bad,
+    Calibration,
+    Custom(String),
+}
+
+/// Tags available without any annotations loaded.
+const BUILTIN_TAGS: [Tag; 3] = [Tag::Good, Tag::Bad, Tag::Calibration];
+
+impl Tag {
+    fn icon(&self) -> &'static str {
+        match self {
+            Tag::Good => "✔",
+            Tag::Bad => "✖",
+            Tag::Calibration => "⚙",
+            Tag::Custom(_) => "🏷",
+        }
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tag::Good => write!(f, "good"),
+            Tag::Bad => write!(f, "bad"),
+            Tag::Calibration => write!(f, "calibration"),
+            Tag::Custom(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// Tags and note of a point or a set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub tags: BTreeSet<Tag>,
+    pub note: String,
+}
+
+impl Annotation {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.note.is_empty()
+    }
+
+    /// Short representation for the file tree (tag icons and a note mark).
+    pub fn icons(&self) -> String {
+        let mut icons = self.tags.iter().map(Tag::icon).collect::<String>();
+        if !self.note.is_empty() {
+            icons.push('📝');
+        }
+        icons
+    }
+
+    /// Hover text with full tags list and note.
+    pub fn hover_text(&self) -> String {
+        let tags = self
+            .tags
+            .iter()
+            .map(Tag::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        match (tags.is_empty(), self.note.is_empty()) {
+            (false, false) => format!("{tags}\n{}", self.note),
+            (false, true) => tags,
+            _ => self.note.clone(),
+        }
+    }
+
+    /// Draws annotation editor.
+    ///
+    /// Returns true if annotation was changed.
+    pub fn editor(&mut self, ui: &mut Ui, id_salt: impl std::hash::Hash) -> bool {
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            for tag in BUILTIN_TAGS {
+                let mut checked = self.tags.contains(&tag);
+                if ui
+                    .checkbox(&mut checked, format!("{} {tag}", tag.icon()))
+                    .changed()
+                {
+                    if checked {
+                        self.tags.insert(tag);
+                    } else {
+                        self.tags.remove(&tag);
+                    }
+                    changed = true;
+                }
+            }
+        });
+
+        let mut removed = None;
+        ui.horizontal_wrapped(|ui| {
+            for tag in self.tags.iter().filter(|tag| matches!(tag, Tag::Custom(_))) {
+                if ui
+                    .small_button(format!("{} {tag} ✖", tag.icon()))
+                    .on_hover_text("Удалить тег")
+                    .clicked()
+                {
+                    removed = Some(tag.clone());
+                }
+            }
+        });
+        if let Some(tag) = removed {
+            self.tags.remove(&tag);
+            changed = true;
+        }
+
+        let id = ui.make_persistent_id(("custom_tag", id_salt));
+        let mut custom = ui.data_mut(|data| data.get_temp::<String>(id).unwrap_or_default());
+        ui.horizontal(|ui| {
+            ui.add_sized([100.0, 20.0], egui::TextEdit::singleline(&mut custom));
+            if ui.button("+ tag").clicked() && !custom.trim().is_empty() {
+                self.tags.insert(Tag::Custom(custom.trim().to_owned()));
+                custom.clear();
+                changed = true;
+            }
+        });
+        ui.data_mut(|data| data.insert_temp(id, custom));
+
+        changed |= ui
+            .add(
+                egui::TextEdit::multiline(&mut self.note)
+                    .hint_text("note")
+                    .desired_rows(3),
+            )
+            .changed();
+
+        changed
+    }
+}
+
+/// Content of a sidecar file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SetAnnotations {
+    pub set: Annotation,
+    /// Point annotations by point file name.
+    pub points: BTreeMap<String, Annotation>,
+}
+
+/// Checks if sidecars can be loaded and saved.
+///
+/// numass-server has no annotations API, so sidecars are available only for local data
+/// (not on the web and not in native `--server` mode).
+pub fn available() -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    return crate::remote::server().is_none();
+    #[cfg(target_arch = "wasm32")]
+    false
+}
+
+/// Loads sidecar of the directory `dir` (empty annotations if it does not exist).
+///
+/// Returns error if the sidecar can not be read or is broken.
+pub async fn load_annotations(dir: PathBuf) -> Result<SetAnnotations, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return match tokio::fs::read_to_string(dir.join(SIDECAR_NAME)).await {
+        Ok(content) => serde_json::from_str(&content).map_err(|err| err.to_string()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(SetAnnotations::default()),
+        Err(err) => Err(err.to_string()),
+    };
+    #[cfg(target_arch = "wasm32")]
+    Err(format!("{dir:?}: annotations are not available on the web"))
+}
+
+/// Writes sidecar of the directory `dir`.
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+pub async fn save_annotations(dir: PathBuf, annotations: SetAnnotations) -> Result<(), String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return tokio::fs::write(
+        dir.join(SIDECAR_NAME),
+        serde_json::to_string_pretty(&annotations).unwrap(),
+    )
+    .await
+    .map_err(|err| err.to_string());
+    #[cfg(target_arch = "wasm32")]
+    Err(format!("{dir:?}: annotations are not available on the web"))
+}
+
+/// Change of an annotation made in UI.
+#[derive(Debug, Clone)]
+pub struct AnnotationEdit {
+    /// Directory of the sidecar.
+    pub dir: PathBuf,
+    /// Point file name (None - set annotation).
+    pub point: Option<String>,
+    pub annotation: Annotation,
+}
+
+impl AnnotationEdit {
+    /// Edit of the `point` annotation.
+    pub fn point(point: &Path, annotation: Annotation) -> Option<Self> {
+        Some(AnnotationEdit {
+            dir: point.parent()?.to_owned(),
+            point: Some(point.file_name()?.to_string_lossy().to_string()),
+            annotation,
+        })
+    }
+}
+
+/// Loaded sidecars by directory (None - still loading or failed).
+#[derive(Debug, Default)]
+pub struct Annotations {
+    pub sets: BTreeMap<PathBuf, Option<SetAnnotations>>,
+    /// Errors of the sidecars which can not be loaded (they are never edited, so the file is kept).
+    failed: BTreeMap<PathBuf, String>,
+    /// Latest not saved content of the sidecars.
+    pending: BTreeMap<PathBuf, SetAnnotations>,
+    /// Directories with a running sidecar writer.
+    writing: BTreeSet<PathBuf>,
+}
+
+impl Annotations {
+    pub fn requested(&self, dir: &Path) -> bool {
+        self.sets.contains_key(dir)
+    }
+
+    /// Stores result of the `dir` sidecar loading.
+    pub fn loaded(&mut self, dir: PathBuf, result: Result<SetAnnotations, String>) {
+        match result {
+            Ok(set) => {
+                self.failed.remove(&dir);
+                self.sets.insert(dir, Some(set));
+            }
+            Err(err) => {
+                tracing::warn!("can't load annotations of {dir:?}: {err}");
+                self.sets.insert(dir.clone(), None);
+                self.failed.insert(dir, err);
+            }
+        }
+    }
+
+    /// Loading error of the `dir` sidecar (None if it is loaded or still loading).
+    pub fn failed(&self, dir: &Path) -> Option<&str> {
+        self.failed.get(dir).map(String::as_str)
+    }
+
+    /// Annotation of the set `dir` (None if not loaded).
+    pub fn set(&self, dir: &Path) -> Option<&Annotation> {
+        self.sets.get(dir)?.as_ref().map(|set| &set.set)
+    }
+
+    /// Annotation of the `point` (None if not loaded or empty).
+    pub fn point(&self, point: &Path) -> Option<&Annotation> {
+        self.sets
+            .get(point.parent()?)?
+            .as_ref()?
+            .points
+            .get(point.file_name()?.to_str()?)
+    }
+
+    /// All tags of the `point` including tags of its set (None if sidecar is not loaded).
+    pub fn point_tags(&self, point: &Path) -> Option<BTreeSet<Tag>> {
+        let set = self.set(point.parent()?)?;
+        let mut tags = set.tags.clone();
+        if let Some(annotation) = self.point(point) {
+            tags.extend(annotation.tags.iter().cloned());
+        }
+        Some(tags)
+    }
+
+    /// Applies `edit` and queues updated sidecar content to be saved.
+    ///
+    /// Edits of not loaded or broken sidecars are ignored (saving them would overwrite the file).
+    /// Returns true if a writer of the sidecar should be started (see [Annotations::next_save]).
+    pub fn apply(&mut self, edit: AnnotationEdit) -> bool {
+        let Some(Some(set)) = self.sets.get_mut(&edit.dir) else {
+            tracing::warn!("annotations of {:?} are not loaded, edit ignored", edit.dir);
+            return false;
+        };
+        match edit.point {
+            Some(point) if edit.annotation.is_empty() => {
+                set.points.remove(&point);
+            }
+            Some(point) => {
+                set.points.insert(point, edit.annotation);
+            }
+            None => set.set = edit.annotation,
+        }
+        self.pending.insert(edit.dir.clone(), set.clone());
+        self.writing.insert(edit.dir)
+    }
+
+    /// Takes the latest not saved content of the `dir` sidecar.
+    ///
+    /// Returns None if everything is saved (the writer of the sidecar should stop).
+    pub fn next_save(&mut self, dir: &Path) -> Option<SetAnnotations> {
+        let set = self.pending.remove(dir);
+        if set.is_none() {
+            self.writing.remove(dir);
+        }
+        set
+    }
+
+    /// Builtin and custom tags of the loaded annotations.
+    pub fn known_tags(&self) -> BTreeSet<Tag> {
+        let mut tags = BUILTIN_TAGS.into_iter().collect::<BTreeSet<_>>();
+        for set in self.sets.values().flatten() {
+            tags.extend(set.set.tags.iter().cloned());
+            for point in set.points.values() {
+                tags.extend(point.tags.iter().cloned());
+            }
+        }
+        tags
+    }
+}
+
+/// File tree filter by tags. Points with not loaded sidecar are hidden while filter is active.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TagFilter {
+    /// Show only points having this tag (own or of their set).
+    pub required: Option<Tag>,
+    /// Hide points tagged as bad (own or set tag).
+    pub hide_bad: bool,
+}
+
+impl TagFilter {
+    pub fn is_active(&self) -> bool {
+        self.required.is_some() || self.hide_bad
+    }
+
+    /// Checks if `point` should be visible.
+    pub fn matches(&self, point: &Path, annotations: &Annotations) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        let Some(tags) = annotations.point_tags(point) else {
+            return false;
+        };
+        !(self.hide_bad && tags.contains(&Tag::Bad))
+            && self
+                .required
+                .as_ref()
+                .is_none_or(|required| tags.contains(required))
+    }
+
+    /// Draws filter editor.
+    pub fn input(&mut self, ui: &mut Ui, known_tags: &BTreeSet<Tag>) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("tag_filter")
+                .selected_text(
+                    self.required
+                        .as_ref()
+                        .map_or("any tag".to_owned(), Tag::to_string),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.required, None, "any tag");
+                    for tag in known_tags {
+                        ui.selectable_value(
+                            &mut self.required,
+                            Some(tag.clone()),
+                            format!("{} {tag}", tag.icon()),
+                        );
+                    }
+                });
+            ui.checkbox(&mut self.hide_bad, "hide bad");
+        });
+    }
+}
@@ -1,5 +1,5 @@
-use std::{collections::BTreeMap, path::Path};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use eframe::{
@@ -9,18 +9,44 @@ use eframe::{
 use egui::Visuals;
 use egui_plot::{HLine, Legend, Plot, PlotPoint, Points, VLine};
 
+use crate::{
+    annotations::{
+        load_annotations, save_annotations, AnnotationEdit, Annotations, TagFilter, SIDECAR_NAME,
+    },
+    coincidence::{CoincidenceView, Coincidences},
+    dead_time::DeadTimeParams,
+    event_filter::EventFilter,
+    events_cache::{ChannelCounts, EventsCache},
+    export::{
+        load_flat_meta, EventsFile, ExportHeader, ExportSink, ExtraColumns, FileNaming, FlatMeta,
+        NameParts, PointEvents,
+    },
+    file_filter::FileFilter,
+    load_meta,
+    memory_budget::{is_evicted, MemoryBudget},
+    meta_filter::{MetaFilter, PointMetaInfo},
+    point_status::{params_hash, ProcessingRecord, ProcessingRecords},
+    point_table::{PointRow, PointStatus, PointTable},
+    processing_log::{FileStage, ProcessingLog},
+    rates::{filtered_time, legacy_rate, point_rates},
+    roots::{RootsAction, RootsHistory},
+    time_window::TimeWindow,
+    tree_selection::{ClickAction, TreeSelection},
+};
+
 use processing::{
     histogram::PointHistogram,
     preprocess::Preprocess,
     storage::LoadState,
-    utils::construct_filename,
     viewer::{ViewerState, EMPTY_POINT},
     widgets::UserInput,
 };
 
 #[cfg(not(target_arch = "wasm32"))]
 use {
-    crate::process_point,
+    crate::remote,
+    crate::root_jobs::{self, ConversionQueue},
+    crate::{process_local_point_events, process_point_events},
     home::home_dir,
     processing::{storage::FSRepr, viewer::PointState},
     tokio::spawn,
@@ -29,31 +55,48 @@ use {
 
 #[cfg(target_arch = "wasm32")]
 use {
-    crate::PointProcessor,
+    crate::worker_pool::{self, WorkerPool},
     eframe::web_sys::window,
-    gloo::{
-        net::http::Request,
-        worker::{oneshot::OneshotBridge, Spawnable},
-    },
+    gloo::net::http::Request,
     processing::{
         storage::{api_url, FSRepr},
         viewer::{PointState, ToROOTOptions, ViewerMode},
     },
-    wasm_bindgen::prelude::*,
     wasm_bindgen_futures::spawn_local as spawn,
 };
 
-#[cfg(target_arch = "wasm32")]
-#[wasm_bindgen]
-extern "C" {
-    fn download(filename: &str, text: &str);
+/// Starts external `viewer` for the point at `filepath` with additional `args`.
+///
+/// Viewers read points from disk, so in `--server` mode the point is downloaded first
+/// (see [crate::point_path]).
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_viewer(viewer: &'static str, filepath: &str, args: Vec<String>) {
+    let filepath = PathBuf::from(filepath);
+    spawn(async move {
+        let Some(local) = crate::point_path(filepath.clone()).await else {
+            tracing::error!("can't load {filepath:?} for {viewer}");
+            return;
+        };
+        if let Err(err) = tokio::process::Command::new(viewer)
+            .arg(local)
+            .args(args)
+            .spawn()
+        {
+            tracing::error!("can't start {viewer}: {err}");
+        }
+    });
 }
 
+/// Number of points meta is loaded for simultaneously (see [DataViewerApp::meta_filter]).
+const META_LOAD_CONCURRENCY: usize = 8;
+
 #[derive(PartialEq, Clone, Copy)]
 pub enum PlotMode {
     Histogram,
     PPT,
     PPV,
+    /// Coincidences between channels (see [CoincidenceView]).
+    Coincidences,
 }
 
 #[derive(Clone, Copy)]
@@ -67,28 +110,166 @@ pub struct ProcessingStatus {
 struct FileTreeState {
     pub need_process: bool,
     pub need_load: bool,
+    /// Visible points whose meta is required by [MetaFilter] but not requested yet.
+    pub need_meta: Vec<String>,
+    /// Visible directories whose annotations sidecar is not requested yet.
+    pub need_annotations: BTreeSet<PathBuf>,
+    /// Annotations changed by user (to be applied and saved).
+    pub annotation_edits: Vec<AnnotationEdit>,
+}
+
+/// Path of the file tree entry.
+fn root_path(root: &FSRepr) -> PathBuf {
+    match root {
+        FSRepr::File { path, .. } | FSRepr::Directory { path, .. } => path.clone(),
+    }
+}
+
+/// Replaces opened roots with their `updated` versions (roots closed meanwhile are not reopened).
+fn merge_roots(roots: &mut [FSRepr], updated: Vec<FSRepr>) {
+    for updated in updated {
+        let path = root_path(&updated);
+        if let Some(root) = roots.iter_mut().find(|root| root_path(root) == path) {
+            *root = updated;
+        }
+    }
+}
+
+/// Collects points (files except set meta) of the loaded part of the file tree.
+fn collect_points(entry: &FSRepr, points: &mut Vec<PathBuf>) {
+    match entry {
+        FSRepr::File { path, .. } => {
+            if !(path.ends_with("meta")
+                || path.ends_with("meta.df")
+                || path.ends_with(SIDECAR_NAME))
+            {
+                points.push(path.clone());
+            }
+        }
+        FSRepr::Directory { children, .. } => {
+            for child in children {
+                collect_points(child, points);
+            }
+        }
+    }
+}
+
+/// Marks all not loaded directories of the tree for loading.
+///
+/// Returns true if some directories were marked.
+fn mark_need_load(entry: &mut FSRepr) -> bool {
+    match entry {
+        FSRepr::File { .. } => false,
+        FSRepr::Directory {
+            children,
+            load_state,
+            ..
+        } => {
+            let mut marked = false;
+            if load_state == &LoadState::NotLoaded {
+                *load_state = LoadState::NeedLoad;
+                marked = true;
+            }
+            for child in children {
+                marked |= mark_need_load(child);
+            }
+            marked
+        }
+    }
+}
+
+/// Combined file tree filter (see [DataViewerApp::file_tree_entry]).
+struct TreeFilter<'a> {
+    files: &'a FileFilter,
+    meta: &'a MetaFilter,
+    /// Requested points meta (None - still loading).
+    metas: &'a BTreeMap<String, Option<PointMetaInfo>>,
+    tags: &'a TagFilter,
+    annotations: &'a Annotations,
+    /// Processing records (source of the points badges).
+    records: &'a ProcessingRecords,
+}
+
+impl TreeFilter<'_> {
+    /// Checks if file `key` should be visible. Set meta files are filtered by name only.
+    fn matches(&self, key: &str, is_set_meta: bool) -> bool {
+        self.files.matches(key)
+            && (is_set_meta
+                || (self
+                    .meta
+                    .matches(self.metas.get(key).and_then(|meta| meta.as_ref()))
+                    && self.tags.matches(Path::new(key), self.annotations)))
+    }
 }
 
 pub struct DataViewerApp {
     #[cfg(not(target_arch = "wasm32"))]
-    pub root: Arc<tokio::sync::Mutex<Option<FSRepr>>>,
+    pub roots: Arc<tokio::sync::Mutex<Vec<FSRepr>>>,
     #[cfg(target_arch = "wasm32")]
-    pub root: Arc<std::sync::Mutex<Option<FSRepr>>>,
+    pub roots: Arc<std::sync::Mutex<Vec<FSRepr>>>,
+    /// Recently opened roots and bookmarks (see [RootsHistory]).
+    roots_history: Arc<Mutex<RootsHistory>>,
 
     select_single: bool,
 
-    /// Фильтр по имени файла (прячет файлы, не содержащие подстроки в имени в виджете файлового дерева)
-    name_contains: String,
+    /// Фильтр файлового дерева (прячет файлы, не подходящие под glob шаблоны)
+    file_filter: FileFilter,
+    /// Фильтр точек по мете (HV, время начала, время набора, тип)
+    meta_filter: MetaFilter,
+    /// Keyboard focus and range selection of the file tree.
+    tree_selection: TreeSelection,
+    /// Loaded tags and notes sidecars (see [crate::annotations]).
+    annotations: Arc<Mutex<Annotations>>,
+    tag_filter: TagFilter,
+    /// Show points as a sortable table instead of the file tree.
+    table_view: bool,
+    point_table: PointTable,
+    /// Meta of the points requested by [DataViewerApp::meta_filter] (None - still loading).
+    point_metas: Arc<Mutex<BTreeMap<String, Option<PointMetaInfo>>>>,
 
     plot_mode: PlotMode,
     processing_params: ViewerState,
+    /// Dead-time correction applied to displayed and exported rates (does not require reprocessing).
+    dead_time: DeadTimeParams,
+    /// Processed time sub-interval of the points.
+    time_window: TimeWindow,
+    /// Filters of the processed events applied before histogramming (do not require reprocessing).
+    event_filter: EventFilter,
+    coincidences: CoincidenceView,
+    /// Collect exported files into a single zip archive (see [ExportSink]).
+    export_zip: bool,
+    /// Export files naming (used by all exporters).
+    naming: FileNaming,
+    /// Files refused to be overwritten or failed to be written by the last exports (shown until
+    /// dismissed).
+    export_refused: Arc<Mutex<Vec<String>>>,
+    /// Extra columns of PPV/PPT exports.
+    extra_columns: ExtraColumns,
+    /// Meta keys found in the scanned points (choices for [DataViewerApp::extra_columns]).
+    meta_keys: Arc<Mutex<BTreeSet<String>>>,
+    /// Parameters restored from an exported file header (applied on the next frame).
+    loaded_header: Arc<Mutex<Option<ExportHeader>>>,
     current_path: Option<String>,
 
     processing_status: Arc<Mutex<ProcessingStatus>>,
+    /// Per-file progress and timings of the last [DataViewerApp::process_points] run.
+    processing_log: Arc<Mutex<ProcessingLog>>,
+    /// Progress of [DataViewerApp::files_export_events_button] export.
+    export_status: Arc<Mutex<ProcessingStatus>>,
     state: Arc<Mutex<BTreeMap<String, PointState>>>,
+    /// Parameters and results of the last processing of each point in [DataViewerApp::state].
+    records: Arc<Mutex<ProcessingRecords>>,
+    /// Processed events of the points (histogram-only changes are applied without reprocessing).
+    events_cache: Arc<Mutex<EventsCache>>,
+    /// Histograms budget of the unchecked points in [DataViewerApp::state].
+    memory: MemoryBudget,
+
+    /// State of the `convert-to-root` jobs started by [DataViewerApp::files_save_root_button].
+    #[cfg(not(target_arch = "wasm32"))]
+    root_jobs: Arc<Mutex<ConversionQueue>>,
 
     #[cfg(target_arch = "wasm32")]
-    processor_pool: Vec<OneshotBridge<PointProcessor>>,
+    processor_pool: Arc<Mutex<WorkerPool>>,
 }
 
 impl DataViewerApp {
@@ -96,20 +277,42 @@ impl DataViewerApp {
     ///
     /// Updated values will be written to [processing_params](DataViewerApp::processing_params) immediately.
     fn params_editor(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        let loaded = if let Some(header) = self.loaded_header.lock().take() {
+            self.processing_params = header.viewer_state();
+            self.dead_time = header.dead_time;
+            self.time_window = header.time_window;
+            self.event_filter = header.event_filter;
+            true
+        } else {
+            false
+        };
+
+        self.params_load_button(ui);
+
         let process = self.processing_params.process.input(ui, ctx);
 
         ui.separator();
 
         let post_process = self.processing_params.post_process.input(ui, ctx);
 
+        let time_window = self.time_window.input(ui, ctx);
+
         ui.separator();
 
         let histogram = self.processing_params.histogram.input(ui, ctx);
 
-        let changed = self.processing_params.changed
-            || (process != self.processing_params.process
-                || post_process != self.processing_params.post_process
-                || histogram != self.processing_params.histogram);
+        let event_filter = self.event_filter.input(ui, ctx);
+
+        let edited = loaded
+            || process != self.processing_params.process
+            || post_process != self.processing_params.post_process
+            || time_window != self.time_window
+            || histogram != self.processing_params.histogram
+            || event_filter != self.event_filter;
+        let changed = self.processing_params.changed || edited;
+
+        self.time_window = time_window;
+        self.event_filter = event_filter;
 
         self.processing_params = ViewerState {
             process,
@@ -117,46 +320,191 @@ impl DataViewerApp {
             histogram,
             changed,
         };
+
+        if edited {
+            self.records.lock().current_hash = params_hash(
+                &self.processing_params,
+                &self.time_window,
+                &self.event_filter,
+            );
+        }
+
+        ui.separator();
+
+        self.dead_time = self.dead_time.input(ui, ctx);
     }
 
-    /// files open button with logic embedded
-    fn files_open_button(&mut self, ui: &mut Ui) {
-        if ui.button("open").clicked() {
-            let root = Arc::clone(&self.root);
+    /// Button restoring processing parameters from the header of a previously exported file.
+    fn params_load_button(&mut self, ui: &mut Ui) {
+        if ui
+            .button("load params")
+            .on_hover_text("Загрузить параметры обработки из заголовка экспортированного файла")
+            .clicked()
+        {
+            let loaded_header = Arc::clone(&self.loaded_header);
 
             spawn(async move {
-                #[cfg(not(target_arch = "wasm32"))]
-                if let Some(root_path) = rfd::FileDialog::new().pick_folder() {
-                    root.lock().await.replace(FSRepr::new(root_path));
-                }
-                #[cfg(target_arch = "wasm32")]
+                if let Some(file) = rfd::AsyncFileDialog::new()
+                    .add_filter("tsv", &["tsv"])
+                    .pick_file()
+                    .await
                 {
-                    let resp = Request::get("/api/root").send().await.unwrap();
-                    let root_local = resp.json::<FSRepr>().await.unwrap();
-                    root.lock().unwrap().replace(root_local);
+                    let content = String::from_utf8_lossy(&file.read().await).to_string();
+                    if let Some(header) = ExportHeader::parse(&content) {
+                        loaded_header.lock().replace(header);
+                    } else {
+                        tracing::warn!("{} has no valid export header", file.file_name());
+                    }
                 }
             });
         }
     }
 
+    /// files open button with logic embedded
+    ///
+    /// Also draws roots menu (opened roots, bookmarks and recent roots).
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - delocked [roots](DataViewerApp::roots) copy.
+    ///
+    fn files_open_button(&mut self, ui: &mut Ui, roots: &[FSRepr]) {
+        if ui.button("open").clicked() {
+            self.open_root(None);
+        }
+
+        let opened = roots.iter().map(root_path).collect::<Vec<_>>();
+        let action = self.roots_history.lock().menu(ui, &opened);
+        match action {
+            Some(RootsAction::Open(path)) => self.open_root(Some(path)),
+            Some(RootsAction::Close(path)) => self.close_root(path),
+            None => {}
+        }
+    }
+
+    /// Closes root at `path` and forgets its points (so they are not processed or exported).
+    fn close_root(&mut self, path: PathBuf) {
+        let in_root = |key: &String| Path::new(key).starts_with(&path);
+
+        let closed = {
+            let mut state = self.state.lock();
+            let closed = state
+                .keys()
+                .filter(|key| in_root(key))
+                .cloned()
+                .collect::<Vec<_>>();
+            state.retain(|key, _| !in_root(key));
+            closed
+        };
+        {
+            let mut records = self.records.lock();
+            records.records.retain(|key, _| !in_root(key));
+            records.channel_counts.retain(|key, _| !in_root(key));
+            records.disk_modified.retain(|key, _| !in_root(key));
+        }
+        self.point_metas.lock().retain(|key, _| !in_root(key));
+        {
+            let mut events_cache = self.events_cache.lock();
+            for key in closed {
+                events_cache.remove(&key);
+            }
+        }
+        if self.current_path.as_ref().is_some_and(in_root) {
+            self.current_path = None;
+        }
+
+        let roots = Arc::clone(&self.roots);
+        spawn(async move {
+            #[cfg(not(target_arch = "wasm32"))]
+            let mut roots = roots.lock().await;
+            #[cfg(target_arch = "wasm32")]
+            let mut roots = roots.lock().unwrap();
+            roots.retain(|root| root_path(root) != path);
+        });
+    }
+
+    /// Opens `path` (or directory picked by user if None) as an additional root.
+    ///
+    /// On the web (and in native `--server` mode) `path` is requested from the server (server root if None).
+    pub fn open_root(&self, path: Option<PathBuf>) {
+        let roots = Arc::clone(&self.roots);
+        let history = Arc::clone(&self.roots_history);
+
+        spawn(async move {
+            #[cfg(not(target_arch = "wasm32"))]
+            let root = if let Some(server) = remote::server() {
+                server.load_root(path.as_deref()).await
+            } else {
+                path.or_else(|| rfd::FileDialog::new().pick_folder())
+                    .map(FSRepr::new)
+            };
+            #[cfg(target_arch = "wasm32")]
+            let root = {
+                let url = match &path {
+                    Some(path) => api_url("api/root", path),
+                    None => "/api/root".to_owned(),
+                };
+                match Request::get(&url).send().await {
+                    Ok(resp) => resp.json::<FSRepr>().await.ok(),
+                    Err(err) => {
+                        tracing::error!("can't load root {url}: {err}");
+                        None
+                    }
+                }
+            };
+
+            if let Some(root) = root {
+                let path = root_path(&root);
+                history.lock().push_recent(&path);
+
+                #[cfg(not(target_arch = "wasm32"))]
+                let mut roots = roots.lock().await;
+                #[cfg(target_arch = "wasm32")]
+                let mut roots = roots.lock().unwrap();
+                if !roots.iter().any(|opened| root_path(opened) == path) {
+                    roots.push(root);
+                }
+            }
+        });
+    }
+
     /// files reload button with logic embedded
     /// # Arguments
     ///
-    /// * `root` - delocked [root](DataViewerApp::root) instance (used to prevent multiple lockings since we should already have a copy).
+    /// * `roots` - delocked [roots](DataViewerApp::roots) copy (used to prevent multiple lockings since we should already have a copy).
+    ///
     ///
-    fn files_reload_button(&mut self, ui: &mut Ui, root: &Option<FSRepr>) {
-        let path = root.clone().map(|root| root.to_filename());
-        if path.is_some() && ui.button("reload").clicked() {
-            if let Some(mut root) = root.clone() {
-                let root_out = Arc::clone(&self.root);
+    /// Also refreshes modification times of the processed points (see [ProcessingRecords::disk_modified]).
+    fn files_reload_button(&mut self, ui: &mut Ui, roots: &[FSRepr]) {
+        if !roots.is_empty() && ui.button("reload").clicked() {
+            let mut roots = roots.to_vec();
+            let roots_out = Arc::clone(&self.roots);
+            let records = Arc::clone(&self.records);
+            let point_metas = Arc::clone(&self.point_metas);
 
-                spawn(async move {
+            spawn(async move {
+                for root in &mut roots {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(server) = remote::server() {
+                        server.update(root).await;
+                        continue;
+                    }
                     root.update_reccurently().await;
-                    if let Ok(mut out) = root_out.try_lock() {
-                        out.replace(root);
+                }
+                if let Ok(mut out) = roots_out.try_lock() {
+                    merge_roots(&mut out, roots);
+                }
+                // points could be rewritten, meta is reloaded on demand
+                point_metas.lock().clear();
+
+                let processed = records.lock().records.keys().cloned().collect::<Vec<_>>();
+                for filepath in processed {
+                    let modified = crate::load_modified_time(filepath.clone().into()).await;
+                    if let Some(modified) = modified {
+                        records.lock().disk_modified.insert(filepath, modified);
                     }
-                });
-            }
+                }
+            });
         }
     }
 
@@ -173,16 +521,44 @@ impl DataViewerApp {
                 ui.label(format!("{processed}/{total}"));
                 ui.spinner();
             });
-        } else if ui.button("apply").clicked() {
-            self.process()
+        } else {
+            if ui
+                .button("apply")
+                .on_hover_text("Обработать новые, устаревшие и измененные точки")
+                .clicked()
+            {
+                self.process()
+            }
+            if ui
+                .button("⟳")
+                .on_hover_text("Переобработать все отмеченные точки")
+                .clicked()
+            {
+                self.process_points(true)
+            }
         }
     }
 
+    /// files save button with logic embedded
+    ///
+    /// Saves tables of the current [PlotMode]. In zip mode all tables (histograms, PPT and PPV)
+    /// are collected into a single archive.
     fn files_save_button(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.export_zip, "zip")
+            .on_hover_text("Собрать все экспортируемые файлы в один zip архив");
+
         if ui.button("save").clicked() {
             let state = self.state.lock().clone();
             let plot_mode = self.plot_mode;
+            let export_zip = self.export_zip;
+            let naming = self.naming.clone();
+            let export_refused = Arc::clone(&self.export_refused);
             let processing_params = self.processing_params.clone();
+            let dead_time = self.dead_time.clone();
+            let channel_counts = self.records.lock().channel_counts.clone();
+            let time_window = self.time_window;
+            let event_filter = self.event_filter.clone();
+            let extra_columns = self.extra_columns.clone();
 
             spawn(async move {
                 #[cfg(not(target_arch = "wasm32"))]
@@ -193,38 +569,312 @@ impl DataViewerApp {
                 let save_folder = Some(PathBuf::new());
 
                 if let Some(save_folder) = save_folder {
+                    let mut metas = BTreeMap::new();
+                    if (export_zip || plot_mode != PlotMode::Histogram)
+                        && !extra_columns.meta_keys.is_empty()
+                    {
+                        for (name, _) in state.iter().filter(|(_, cache)| cache.opened) {
+                            if let Some(meta) = load_flat_meta(PathBuf::from(name)).await {
+                                metas.insert(name.clone(), meta);
+                            }
+                        }
+                    }
+
                     let state_sorted = {
                         let mut state = state.iter().collect::<Vec<_>>();
                         state.sort_by(|(key_1, _), (key_2, _)| natord::compare(key_1, key_2));
                         state
                     };
 
-                    match plot_mode {
-                        PlotMode::Histogram => {
-                            DataViewerApp::files_save_histograms(&save_folder, &state_sorted)
-                        }
-                        PlotMode::PPT => {
-                            DataViewerApp::files_save_ppt(
-                                &save_folder,
-                                &state_sorted,
-                                &processing_params,
-                            );
-                        }
-                        PlotMode::PPV => {
-                            DataViewerApp::files_save_ppv(
-                                &save_folder,
-                                &state_sorted,
-                                &processing_params,
-                            );
+                    let header = ExportHeader::new(
+                        &processing_params,
+                        &dead_time,
+                        &time_window,
+                        &event_filter,
+                        &state_sorted,
+                    );
+
+                    let mut sink =
+                        ExportSink::new(save_folder, export_zip.then_some("export"), naming);
+
+                    if export_zip || plot_mode == PlotMode::Histogram {
+                        DataViewerApp::files_save_histograms(&mut sink, &state_sorted, &header);
+                    }
+                    if export_zip || plot_mode == PlotMode::PPT {
+                        DataViewerApp::files_save_ppt(
+                            &mut sink,
+                            &state_sorted,
+                            &processing_params,
+                            &dead_time,
+                            &channel_counts,
+                            &extra_columns,
+                            &metas,
+                            &header,
+                        );
+                    }
+                    if export_zip || plot_mode == PlotMode::PPV {
+                        DataViewerApp::files_save_ppv(
+                            &mut sink,
+                            &state_sorted,
+                            &processing_params,
+                            &dead_time,
+                            &channel_counts,
+                            &extra_columns,
+                            &metas,
+                            &header,
+                        );
+                    }
+
+                    export_refused.lock().extend(sink.finish());
+                }
+            });
+        }
+    }
+
+    /// Export settings editor (file naming and extra PPV/PPT columns) with meta scan logic embedded
+    fn export_settings_editor(&mut self, ui: &mut Ui) {
+        {
+            let mut export_refused = self.export_refused.lock();
+            if !export_refused.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        Color32::RED,
+                        format!("{} files not exported", export_refused.len()),
+                    )
+                    .on_hover_text(export_refused.join("\n"));
+                    if ui.button("x").clicked() {
+                        export_refused.clear();
+                    }
+                });
+            }
+        }
+
+        egui::CollapsingHeader::new("export settings")
+            .id_salt("export_settings")
+            .show(ui, |ui| {
+                self.naming.input(ui);
+
+                ui.separator();
+
+                if ui
+                    .button("scan meta")
+                    .on_hover_text("Загрузить meta открытых точек для выбора колонок")
+                    .clicked()
+                {
+                    let opened = self
+                        .state
+                        .lock()
+                        .iter()
+                        .filter(|(_, cache)| cache.opened)
+                        .map(|(name, _)| name.clone())
+                        .collect::<Vec<_>>();
+                    let meta_keys = Arc::clone(&self.meta_keys);
+
+                    spawn(async move {
+                        for name in opened {
+                            if let Some(meta) = load_flat_meta(PathBuf::from(name)).await {
+                                meta_keys.lock().extend(meta.into_keys());
+                            }
                         }
+                    });
+                }
+
+                let meta_keys = self.meta_keys.lock().clone();
+                self.extra_columns.input(ui, &meta_keys);
+            });
+    }
+
+    /// Events export button with logic embedded
+    ///
+    /// Each opened point is processed with current parameters one by one and its events are saved
+    /// to `{name}.events.tsv` and `{name}.events.msgpack` (see [EventsFile]) files
+    /// (collected into a zip archive if [DataViewerApp::export_zip] is set).
+    fn files_export_events_button(&mut self, ui: &mut Ui) {
+        let ProcessingStatus {
+            running,
+            total,
+            processed,
+        } = *self.export_status.lock();
+
+        if running {
+            ui.horizontal(|ui| {
+                ui.label(format!("events: {processed}/{total}"));
+                ui.spinner();
+            });
+            return;
+        }
+
+        if ui
+            .button("export events")
+            .on_hover_text("Сохранить события открытых точек (tsv + msgpack)")
+            .clicked()
+        {
+            let state = self.state.lock().clone();
+            let processing_params = self.processing_params.clone();
+            let dead_time = self.dead_time.clone();
+            let time_window = self.time_window;
+            let event_filter = self.event_filter.clone();
+            let export_zip = self.export_zip;
+            let naming = self.naming.clone();
+            let export_refused = Arc::clone(&self.export_refused);
+            let status = Arc::clone(&self.export_status);
+            #[cfg(target_arch = "wasm32")]
+            let processor_pool = Arc::clone(&self.processor_pool);
+
+            spawn(async move {
+                #[cfg(not(target_arch = "wasm32"))]
+                let save_folder = rfd::FileDialog::new()
+                    .set_directory(home_dir().unwrap())
+                    .pick_folder();
+                #[cfg(target_arch = "wasm32")]
+                let save_folder = Some(PathBuf::new());
+
+                let Some(save_folder) = save_folder else {
+                    return;
+                };
+
+                let (header, opened) = {
+                    let mut state_sorted = state.iter().collect::<Vec<_>>();
+                    state_sorted.sort_by(|(key_1, _), (key_2, _)| natord::compare(key_1, key_2));
+
+                    let opened = state_sorted
+                        .iter()
+                        .filter(|(_, cache)| cache.opened)
+                        .map(|(name, _)| (*name).clone())
+                        .collect::<Vec<_>>();
+
+                    (
+                        ExportHeader::new(
+                            &processing_params,
+                            &dead_time,
+                            &time_window,
+                            &event_filter,
+                            &state_sorted,
+                        ),
+                        opened,
+                    )
+                };
+
+                if opened.is_empty() {
+                    return;
+                }
+
+                {
+                    let mut status = status.lock();
+                    status.total = opened.len();
+                    status.processed = 0;
+                    status.running = true;
+                }
+
+                let mut sink = ExportSink::new(save_folder, export_zip.then_some("events"), naming);
+
+                for name in opened {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let events = process_point_events(
+                        PathBuf::from(&name),
+                        processing_params.process.clone(),
+                        processing_params.post_process,
+                        time_window,
+                    )
+                    .await
+                    .flatten();
+                    #[cfg(target_arch = "wasm32")]
+                    let events = worker_pool::run(
+                        Arc::clone(&processor_pool),
+                        (
+                            PathBuf::from(&name),
+                            processing_params.process.clone(),
+                            processing_params.post_process,
+                            time_window,
+                            processing_params.histogram.clone(),
+                            event_filter.clone(),
+                            true,
+                        ),
+                    )
+                    .await
+                    .and_then(|(_, events)| events);
+
+                    if let Some(events) = events {
+                        let point = PointEvents::collect(
+                            PathBuf::from(&name),
+                            events,
+                            processing_params.post_process.cut_bad_blocks,
+                            time_window,
+                            &event_filter,
+                        )
+                        .await;
+                        let header = header.for_source(&name);
+
+                        let parts = NameParts {
+                            hv: Some(point.hv),
+                            date: chrono::DateTime::parse_from_rfc3339(&point.start_time)
+                                .ok()
+                                .map(|start_time| start_time.naive_utc()),
+                            ..NameParts::point(&name, "events", None)
+                        };
+
+                        DataViewerApp::save_text_file(
+                            &mut sink,
+                            &parts,
+                            Some("events.tsv"),
+                            &header,
+                            &(point.header_text() + &point.to_tsv()),
+                        );
+
+                        let binary =
+                            rmp_serde::to_vec_named(&EventsFile { header, point }).unwrap();
+                        DataViewerApp::save_binary_file(
+                            &mut sink,
+                            &parts,
+                            Some("events.msgpack"),
+                            &binary,
+                        );
+                    } else {
+                        tracing::warn!("{name} can't be processed, events are not exported");
                     }
+
+                    crate::inc_status(Arc::clone(&status));
                 }
+
+                export_refused.lock().extend(sink.finish());
             });
         }
     }
 
+    /// ROOT export button with logic embedded
+    ///
+    /// - Native: converts opened files with `convert-to-root` processes (see [root_jobs]),
+    ///   shows progress and a summary of failed conversions.
+    /// - WASM: opens numass-server conversion endpoint for each opened file.
     fn files_save_root_button(&mut self, ui: &mut Ui) {
-        if ui.button("save(root)").clicked() {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut root_jobs = self.root_jobs.lock();
+            if root_jobs.running() {
+                root_jobs.show(ui);
+                return;
+            }
+            if root_jobs.starting {
+                ui.spinner();
+                return;
+            }
+            if root_jobs.show(ui) {
+                root_jobs.jobs.clear();
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let converter_in_path = which(root_jobs::CONVERTER).is_ok();
+        #[cfg(target_arch = "wasm32")]
+        let converter_in_path = true;
+
+        let button = ui
+            .add_enabled(converter_in_path, egui::Button::new("save(root)"))
+            .on_disabled_hover_ui(|ui| {
+                ui.colored_label(Color32::RED, "convert-to-root must be in PATH");
+            });
+
+        if button.clicked() {
             let state = self.state.lock().clone();
 
             #[cfg(target_arch = "wasm32")]
@@ -248,150 +898,521 @@ impl DataViewerApp {
             #[cfg(not(target_arch = "wasm32"))]
             {
                 let processing_params = self.processing_params.clone();
+                let naming = self.naming.clone();
+                let root_jobs = Arc::clone(&self.root_jobs);
+                root_jobs.lock().starting = true;
 
                 spawn(async move {
                     let save_folder = rfd::FileDialog::new()
                         .set_directory(home_dir().unwrap())
                         .pick_folder();
-                    // #[cfg(target_arch = "wasm32")]
-                    // let save_folder = Some(PathBuf::new());
 
                     if let Some(save_folder) = save_folder {
-                        let state_sorted = {
-                            let mut state = state.iter().collect::<Vec<_>>();
-                            state.sort_by(|(key_1, _), (key_2, _)| natord::compare(key_1, key_2));
-                            state
-                        };
+                        let mut sources = state
+                            .iter()
+                            .filter(|(_, cache)| cache.opened)
+                            .map(|(name, cache)| {
+                                let parts =
+                                    NameParts::point(name, "root", cache.preprocess.as_ref());
+                                let out_name = naming.render(&parts, Some("root"));
+                                (name.clone(), save_folder.join(out_name))
+                            })
+                            .collect::<Vec<_>>();
+                        sources.sort_by(|(key_1, _), (key_2, _)| natord::compare(key_1, key_2));
+
+                        let concurrency = std::thread::available_parallelism()
+                            .map(|n| n.get())
+                            .unwrap_or(1);
+
+                        root_jobs::start_conversion(
+                            Arc::clone(&root_jobs),
+                            sources,
+                            processing_params.process,
+                            processing_params.post_process,
+                            concurrency,
+                        );
+                    }
+                    root_jobs.lock().starting = false;
+                });
+            };
+        }
+    }
+
+    /// Draws file editor and handles user inputs.
+    fn files_editor(&mut self, ui: &mut Ui) {
+        let mut roots_copy = {
+            if let Ok(roots) = self.roots.try_lock() {
+                roots.clone()
+            } else {
+                ui.spinner();
+                return;
+            }
+        };
+
+        let bulk_action = ui
+            .horizontal(|ui| {
+                ui.checkbox(&mut self.select_single, "select single");
+                ui.checkbox(&mut self.table_view, "table");
+                if self.table_view {
+                    None
+                } else {
+                    self.tree_selection.input(ui)
+                }
+            })
+            .inner;
+
+        let needs_to_be_marked = self.file_filter.input(ui);
+
+        {
+            let (meta_types, loading) = {
+                let metas = self.point_metas.lock();
+                let meta_types = metas
+                    .values()
+                    .flatten()
+                    .map(|meta| meta.meta_type.clone())
+                    .collect::<BTreeSet<_>>();
+                (
+                    meta_types.into_iter().collect::<Vec<_>>(),
+                    metas.values().filter(|meta| meta.is_none()).count(),
+                )
+            };
+            self.meta_filter.input(ui, &meta_types, loading);
+        }
+
+        if crate::annotations::available() {
+            let known_tags = self.annotations.lock().known_tags();
+            self.tag_filter.input(ui, &known_tags);
+        }
+
+        ui.horizontal(|ui| {
+            self.files_open_button(ui, &roots_copy);
+
+            self.files_reload_button(ui, &roots_copy);
+
+            self.files_process_button(ui);
+
+            if ui.button("clear").clicked() {
+                self.state.lock().clear()
+            }
+
+            self.files_save_button(ui);
+        });
+
+        ui.horizontal(|ui| {
+            self.files_save_root_button(ui);
+
+            self.files_export_events_button(ui);
+        });
+
+        self.export_settings_editor(ui);
+
+        self.memory_editor(ui);
+
+        self.processing_log.lock().input(ui);
+
+        #[cfg(target_arch = "wasm32")]
+        self.processor_pool.lock().input(ui);
+
+        if !roots_copy.is_empty() {
+            let mut state_after = FileTreeState {
+                need_load: false,
+                need_process: false,
+                need_meta: vec![],
+                need_annotations: BTreeSet::new(),
+                annotation_edits: vec![],
+            };
+
+            if self.table_view {
+                self.files_table(ui, &mut roots_copy, needs_to_be_marked, &mut state_after);
+            } else {
+                self.tree_selection.begin_frame();
+
+                egui::containers::ScrollArea::new([false, true]).show(ui, |ui| {
+                    let metas = self.point_metas.lock();
+                    let annotations = self.annotations.lock();
+                    let records = self.records.lock();
+                    let filter = TreeFilter {
+                        files: &self.file_filter,
+                        meta: &self.meta_filter,
+                        metas: &metas,
+                        tags: &self.tag_filter,
+                        annotations: &annotations,
+                        records: &records,
+                    };
+
+                    let mut state = self.state.lock();
+                    for root in &mut roots_copy {
+                        DataViewerApp::file_tree_entry(
+                            ui,
+                            root,
+                            &self.select_single,
+                            &filter,
+                            &mut self.tree_selection,
+                            needs_to_be_marked,
+                            &mut state,
+                            &mut state_after,
+                        );
+                    }
+                });
+
+                let toggled = self.tree_selection.keyboard(ui.ctx());
+                state_after.need_process |= self.tree_selection.apply(
+                    &mut self.state.lock(),
+                    toggled,
+                    bulk_action,
+                    self.select_single,
+                    EMPTY_POINT,
+                    |cache| &mut cache.opened,
+                );
+            }
+
+            if !state_after.need_meta.is_empty() {
+                self.load_point_metas(state_after.need_meta);
+            }
+
+            if !state_after.need_annotations.is_empty() {
+                self.load_annotations(state_after.need_annotations);
+            }
+
+            for edit in state_after.annotation_edits {
+                self.edit_annotation(edit);
+            }
+
+            if state_after.need_process && (self.select_single || self.evicted_checked()) {
+                self.process();
+            }
+
+            if state_after.need_load {
+                let roots_out = Arc::clone(&self.roots);
+
+                spawn(async move {
+                    for root in &mut roots_copy {
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if let Some(server) = remote::server() {
+                            server.expand(root).await;
+                            continue;
+                        }
+                        root.expand_reccurently().await;
+                    }
+                    if let Ok(mut out) = roots_out.try_lock() {
+                        merge_roots(&mut out, roots_copy);
+                    }
+                });
+            }
+        }
+    }
+
+    /// Enforces [DataViewerApp::memory] budget and draws memory usage readout.
+    fn memory_editor(&mut self, ui: &mut Ui) {
+        self.memory.enforce(&mut self.state.lock());
+        self.memory.input(ui, &mut self.events_cache.lock());
+    }
+
+    /// Checks if some checked points have evicted histograms (and were processed without errors).
+    ///
+    /// Such points are processed again when checked (histograms are rebuilt from
+    /// the [EventsCache] if the events are still cached). Reload is postponed while there are
+    /// not applied parameter changes.
+    fn evicted_checked(&self) -> bool {
+        if self.processing_params.changed {
+            return false;
+        }
+        let state = self.state.lock();
+        let records = self.records.lock();
+        state.iter().any(|(key, cache)| {
+            cache.opened
+                && is_evicted(cache)
+                && records.records.get(key).is_some_and(|record| !record.error)
+        })
+    }
+
+    /// Draws points of the loaded directories as a [PointTable] (alternative to [DataViewerApp::file_tree_entry]).
+    fn files_table(
+        &mut self,
+        ui: &mut Ui,
+        roots: &mut [FSRepr],
+        needs_to_be_marked: bool,
+        state_after: &mut FileTreeState,
+    ) {
+        if ui
+            .button("load all")
+            .on_hover_text("Загрузить все вложенные папки")
+            .clicked()
+        {
+            for root in roots.iter_mut() {
+                state_after.need_load |= mark_need_load(root);
+            }
+        }
+
+        let mut points = vec![];
+        for root in roots.iter() {
+            collect_points(root, &mut points);
+        }
+
+        let cut_bad_blocks = self.processing_params.post_process.cut_bad_blocks;
+
+        let rows = {
+            let metas = self.point_metas.lock();
+            let annotations = self.annotations.lock();
+            let records = self.records.lock();
+            let filter = TreeFilter {
+                files: &self.file_filter,
+                meta: &self.meta_filter,
+                metas: &metas,
+                tags: &self.tag_filter,
+                annotations: &annotations,
+                records: &records,
+            };
+            let mut state = self.state.lock();
+
+            points
+                .into_iter()
+                .filter_map(|path| {
+                    let key = path.to_str()?.to_owned();
 
-                        // let mut out_names = String::new();
-
-                        for (name, cache) in state_sorted.iter() {
-                            if let PointState { opened: true, .. } = cache {
-                                let out_name = construct_filename(name, Some("root"));
-
-                                // if cache.opened {
-                                //     out_names += &format!("{}\n", out_name);
-                                // }
-
-                                let mut command = tokio::process::Command::new("convert-to-root");
-                                command
-                                    .arg(name)
-                                    .arg("--process")
-                                    .arg(serde_json::to_string(&processing_params.process).unwrap())
-                                    .arg("--postprocess")
-                                    .arg(
-                                        serde_json::to_string(&processing_params.post_process)
-                                            .unwrap(),
-                                    )
-                                    .arg("--output")
-                                    .arg(save_folder.join(PathBuf::from(out_name)));
-
-                                command.spawn().unwrap();
-                            }
+                    if let Some(dir) = path.parent() {
+                        if !filter.annotations.requested(dir) {
+                            state_after.need_annotations.insert(dir.to_owned());
                         }
-                        // DataViewerApp::save_text_file(&save_folder, "opened", Some("tsv"), &out_names);
                     }
-                });
-            };
-        }
-    }
+                    if filter.meta.enabled
+                        && filter.files.matches(&key)
+                        && !filter.metas.contains_key(&key)
+                    {
+                        state_after.need_meta.push(key.clone());
+                    }
+                    if !filter.matches(&key, false) {
+                        return None;
+                    }
 
-    /// Draws file editor and handles user inputs.
-    fn files_editor(&mut self, ui: &mut Ui) {
-        let mut root_copy = {
-            if let Ok(root) = self.root.try_lock() {
-                root.clone()
-            } else {
-                ui.spinner();
-                return;
-            }
+                    let cache = state.entry(key.clone()).or_insert(EMPTY_POINT);
+                    if needs_to_be_marked {
+                        cache.opened = true;
+                    }
+                    let meta = metas.get(&key).and_then(Option::as_ref);
+
+                    Some(PointRow {
+                        set: path
+                            .parent()
+                            .and_then(|parent| parent.file_name())
+                            .map(|set| set.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                        name: path.file_name()?.to_string_lossy().to_string(),
+                        hv: cache
+                            .preprocess
+                            .as_ref()
+                            .map(|preprocess| preprocess.hv as f64)
+                            .or(meta.and_then(|meta| meta.hv)),
+                        start_time: cache
+                            .preprocess
+                            .as_ref()
+                            .map(|preprocess| preprocess.start_time)
+                            .or(meta.and_then(|meta| meta.start_time)),
+                        acquisition_time: cache
+                            .preprocess
+                            .as_ref()
+                            .map(|preprocess| preprocess.acquisition_time as f64 * 1e-9)
+                            .or(meta.and_then(|meta| meta.acquisition_time)),
+                        counts: cache.counts,
+                        rate: point_rates(
+                            cache,
+                            filter.records.channel_counts.get(&key),
+                            cut_bad_blocks,
+                            &self.dead_time,
+                            &self.time_window,
+                            &self.event_filter,
+                        )
+                        .map(|(_, rate)| rate),
+                        status: match (cache.opened, &cache.histogram, cache.counts) {
+                            (false, _, _) => PointStatus::Closed,
+                            (true, None, None) => PointStatus::Pending,
+                            // points without events have zero counts and no histogram
+                            (true, _, _) => PointStatus::Processed,
+                        },
+                        opened: cache.opened,
+                        key,
+                    })
+                })
+                .collect::<Vec<_>>()
         };
 
-        let mut needs_to_be_marked = false;
+        let changes = self.point_table.show(ui, rows);
+        if changes.is_empty() {
+            return;
+        }
 
-        ui.checkbox(&mut self.select_single, "select single");
+        let mut state = self.state.lock();
+        let exclusive_point = changes
+            .iter()
+            .find(|(_, opened)| *opened)
+            .filter(|_| self.select_single);
 
-        ui.horizontal(|ui| {
-            ui.label("name contains:");
-            ui.add_sized(
-                [100.0, 20.0],
-                egui::TextEdit::singleline(&mut self.name_contains),
-            );
-            needs_to_be_marked = ui
-                .button("+")
-                .on_hover_text("Выделить все видимые файлы")
-                .clicked();
-        });
+        if let Some((point, _)) = exclusive_point {
+            for (key, cache) in state.iter_mut() {
+                cache.opened = key == point;
+            }
+        } else {
+            for (key, opened) in changes {
+                state.entry(key).or_insert(EMPTY_POINT).opened = opened;
+            }
+        }
+        state_after.need_process = true;
+    }
 
-        ui.horizontal(|ui| {
-            self.files_open_button(ui);
+    /// Loads annotations sidecars of the `dirs` in background (if sidecars are available).
+    fn load_annotations(&self, dirs: BTreeSet<PathBuf>) {
+        if !crate::annotations::available() {
+            return;
+        }
+        {
+            let mut annotations = self.annotations.lock();
+            for dir in &dirs {
+                annotations.sets.insert(dir.clone(), None);
+            }
+        }
 
-            self.files_reload_button(ui, &root_copy);
+        for dir in dirs {
+            let annotations = Arc::clone(&self.annotations);
+            spawn(async move {
+                let result = load_annotations(dir.clone()).await;
+                annotations.lock().loaded(dir, result);
+            });
+        }
+    }
 
-            self.files_process_button(ui);
+    /// Applies annotation change and saves its sidecar.
+    ///
+    /// Sidecar of a directory is written by a single task, which saves the latest content
+    /// until there are no more changes.
+    fn edit_annotation(&self, edit: AnnotationEdit) {
+        let dir = edit.dir.clone();
+        if !self.annotations.lock().apply(edit) {
+            return;
+        }
 
-            if ui.button("clear").clicked() {
-                self.state.lock().clear()
+        let annotations = Arc::clone(&self.annotations);
+        spawn(async move {
+            loop {
+                let Some(set) = annotations.lock().next_save(&dir) else {
+                    break;
+                };
+                if let Err(err) = save_annotations(dir.clone(), set).await {
+                    tracing::error!("can't save annotations of {dir:?}: {err}");
+                }
             }
-
-            self.files_save_button(ui);
         });
+    }
 
-        self.files_save_root_button(ui);
-
-        egui::containers::ScrollArea::new([false, true]).show(ui, |ui| {
-            if let Some(root) = &mut root_copy {
-                let mut state_after = FileTreeState {
-                    need_load: false,
-                    need_process: false,
-                };
+    /// Tags and note editor of the marked `point` with logic embedded.
+    fn point_notes_button(&self, ui: &mut Ui, point: &Path) {
+        let Some(dir) = point.parent() else {
+            return;
+        };
+        if !crate::annotations::available() {
+            return;
+        }
 
-                DataViewerApp::file_tree_entry(
-                    ui,
-                    root,
-                    &self.select_single,
-                    &self.name_contains,
-                    needs_to_be_marked,
-                    &mut self.state.lock(),
-                    &mut state_after,
-                );
+        let (requested, loaded, failed, annotation) = {
+            let annotations = self.annotations.lock();
+            (
+                annotations.requested(dir),
+                annotations.set(dir).is_some(),
+                annotations.failed(dir).map(str::to_owned),
+                annotations.point(point).cloned().unwrap_or_default(),
+            )
+        };
+        if !requested {
+            self.load_annotations(BTreeSet::from([dir.to_owned()]));
+        }
 
-                if state_after.need_process && self.select_single {
-                    self.process();
+        let mut edit = None;
+        ui.menu_button(format!("notes {}", annotation.icons()), |ui| {
+            if loaded {
+                let mut annotation = annotation.clone();
+                if annotation.editor(ui, point) {
+                    edit = AnnotationEdit::point(point, annotation);
                 }
+            } else if let Some(err) = &failed {
+                ui.colored_label(Color32::RED, format!("{SIDECAR_NAME}: {err}"));
+            } else {
+                ui.spinner();
+            }
+        })
+        .response
+        .on_hover_text(annotation.hover_text());
+
+        if let Some(edit) = edit {
+            self.edit_annotation(edit);
+        }
+    }
 
-                if state_after.need_load {
-                    let root_out = Arc::clone(&self.root);
-                    let mut root = root.clone();
+    /// Loads meta of the `points` in background (for [DataViewerApp::meta_filter]).
+    fn load_point_metas(&self, points: Vec<String>) {
+        {
+            let mut metas = self.point_metas.lock();
+            for point in &points {
+                metas.insert(point.clone(), None);
+            }
+        }
 
-                    spawn(async move {
-                        root.expand_reccurently().await;
-                        if let Ok(mut out) = root_out.try_lock() {
-                            out.replace(root);
+        let queue = Arc::new(Mutex::new(VecDeque::from(points)));
+        for _ in 0..META_LOAD_CONCURRENCY {
+            let queue = Arc::clone(&queue);
+            let point_metas = Arc::clone(&self.point_metas);
+            spawn(async move {
+                loop {
+                    let Some(point) = queue.lock().pop_front() else {
+                        break;
+                    };
+                    let meta = if let Some(meta) = load_meta(&PathBuf::from(&point)).await {
+                        PointMetaInfo::from_meta(&meta)
+                    } else {
+                        tracing::warn!("can't load meta of {point}");
+                        PointMetaInfo {
+                            meta_type: "unknown".to_owned(),
+                            ..Default::default()
                         }
-                    });
+                    };
+                    point_metas.lock().insert(point, Some(meta));
                 }
-            }
-        });
+            });
+        }
     }
 
     /// Recursive file tree drawer with logic embedded
+    #[allow(clippy::too_many_arguments)]
     fn file_tree_entry(
         ui: &mut egui::Ui,
         entry: &mut FSRepr,
         select_single: &bool,
-        name_contains: &str,
+        filter: &TreeFilter,
+        selection: &mut TreeSelection,
         needs_to_be_marked: bool,
         opened_files: &mut BTreeMap<String, PointState>,
         state_after: &mut FileTreeState,
     ) {
         match entry {
             FSRepr::File { path, .. } => {
+                if path.ends_with(SIDECAR_NAME) {
+                    return;
+                }
+
                 let key = path.to_str().unwrap().to_string();
-                if name_contains.is_empty() || key.contains(name_contains) {
+                let is_set_meta = path.ends_with("meta") || path.ends_with("meta.df");
+
+                if filter.meta.enabled
+                    && !is_set_meta
+                    && filter.files.matches(&key)
+                    && !filter.metas.contains_key(&key)
+                {
+                    state_after.need_meta.push(key.clone());
+                }
+
+                if filter.matches(&key, is_set_meta) {
                     let cache = opened_files.entry(key.clone()).or_insert(EMPTY_POINT);
                     let mut change_set = None;
                     let mut exclusive_point = None;
+                    let mut range = None;
 
                     ui.horizontal(|ui| {
                         if needs_to_be_marked {
@@ -399,26 +1420,69 @@ impl DataViewerApp {
                         }
 
                         if ui.checkbox(&mut cache.opened, "").changed() {
-                            if cache.opened && *select_single {
-                                exclusive_point = Some(key)
+                            match selection.clicked(&key, ui.input(|i| i.modifiers)) {
+                                ClickAction::Single => {
+                                    if cache.opened && *select_single {
+                                        exclusive_point = Some(key.clone())
+                                    }
+
+                                    if is_set_meta {
+                                        change_set = Some(cache.opened)
+                                    };
+                                }
+                                ClickAction::Add => {}
+                                ClickAction::Range(keys) => range = Some((keys, cache.opened)),
                             }
+                        }
 
-                            if path.ends_with("meta") || path.ends_with("meta.df") {
-                                change_set = Some(cache.opened)
-                            };
+                        if !is_set_meta {
+                            filter.records.badge(&key, cache).show(ui);
                         }
 
                         let filename = path.file_name().unwrap().to_str().unwrap();
-
                         #[cfg(not(target_arch = "wasm32"))]
-                        ui.label(filename);
+                        let label = selection.label(ui, &key, filename);
                         #[cfg(target_arch = "wasm32")]
-                        {
-                            ui.hyperlink_to(filename, api_url("api/meta", path));
+                        let label = selection.link(ui, &key, filename, api_url("api/meta", path));
+
+                        let annotation = filter.annotations.point(path);
+                        if let Some(annotation) = annotation {
+                            ui.label(annotation.icons())
+                                .on_hover_text(annotation.hover_text());
+                        }
+                        if !is_set_meta && crate::annotations::available() {
+                            let dir = path.parent();
+                            let loaded =
+                                dir.is_some_and(|dir| filter.annotations.set(dir).is_some());
+                            let failed = dir.and_then(|dir| filter.annotations.failed(dir));
+                            label.context_menu(|ui| {
+                                if let Some(err) = failed {
+                                    ui.colored_label(
+                                        Color32::RED,
+                                        format!("{SIDECAR_NAME}: {err}"),
+                                    );
+                                    return;
+                                }
+                                if !loaded {
+                                    ui.spinner();
+                                    return;
+                                }
+                                let mut annotation = annotation.cloned().unwrap_or_default();
+                                if annotation.editor(ui, &key) {
+                                    state_after
+                                        .annotation_edits
+                                        .extend(AnnotationEdit::point(path, annotation));
+                                }
+                            });
                         }
                     });
 
-                    if let Some(point) = exclusive_point {
+                    if let Some((keys, opened)) = range {
+                        for key in keys {
+                            opened_files.entry(key).or_insert(EMPTY_POINT).opened = opened;
+                        }
+                        state_after.need_process = true;
+                    } else if let Some(point) = exclusive_point {
                         for (key, cache) in opened_files.iter_mut() {
                             if key != &point {
                                 cache.opened = false;
@@ -446,22 +1510,49 @@ impl DataViewerApp {
                 load_state,
                 ..
             } => {
-                let header =
-                    egui::CollapsingHeader::new(path.file_name().unwrap().to_str().unwrap())
-                        .id_salt(path.to_str().unwrap())
-                        .show(ui, |ui| {
-                            for child in children {
-                                DataViewerApp::file_tree_entry(
-                                    ui,
-                                    child,
-                                    select_single,
-                                    name_contains,
-                                    needs_to_be_marked,
-                                    opened_files,
-                                    state_after,
-                                )
+                if !filter.annotations.requested(path) {
+                    state_after.need_annotations.insert(path.clone());
+                }
+                let set_annotation = filter.annotations.set(path);
+
+                let mut title = path.file_name().unwrap().to_str().unwrap().to_owned();
+                if let Some(annotation) = set_annotation.filter(|annotation| !annotation.is_empty())
+                {
+                    title = format!("{title} {}", annotation.icons());
+                }
+
+                let header = egui::CollapsingHeader::new(title)
+                    .id_salt(path.to_str().unwrap())
+                    .show(ui, |ui| {
+                        for child in children {
+                            DataViewerApp::file_tree_entry(
+                                ui,
+                                child,
+                                select_single,
+                                filter,
+                                selection,
+                                needs_to_be_marked,
+                                opened_files,
+                                state_after,
+                            )
+                        }
+                    });
+
+                if let Some(annotation) = set_annotation {
+                    header
+                        .header_response
+                        .on_hover_text(annotation.hover_text())
+                        .context_menu(|ui| {
+                            let mut annotation = annotation.clone();
+                            if annotation.editor(ui, &*path) {
+                                state_after.annotation_edits.push(AnnotationEdit {
+                                    dir: path.clone(),
+                                    point: None,
+                                    annotation,
+                                });
                             }
                         });
+                }
 
                 if header.fully_open() && load_state == &LoadState::NotLoaded {
                     *load_state = LoadState::NeedLoad;
@@ -476,48 +1567,92 @@ impl DataViewerApp {
     /// Result will be saved in `PPV.tsv` file in a place according [DataViewerApp::save_text_file]
     ///
     /// # Arguments
-    /// * `save_folder` - Directory where the file should be saved (on wasm side can be any).
+    /// * `sink` - Destination of the exported file.
     /// * `state_sorted` - A ref copy of [DataViewerApp::state] converted to vec (must be sorted for pretty results).
     /// * `processing_params` - A ref copy of [ViewerState] to get processing parameters.
+    /// * `dead_time` - Dead-time correction, if enabled `count_rate_corrected` column is added.
+    /// * `extra_columns` - Optional columns appended to each row.
+    /// * `metas` - Flattened metas of the points (source of the meta columns).
+    /// * `header` - Provenance header prepended to the file.
     ///
+    #[allow(clippy::too_many_arguments)]
     fn files_save_ppv(
-        save_folder: &Path,
+        sink: &mut ExportSink,
         state_sorted: &Vec<(&String, &PointState)>,
         processing_params: &ViewerState,
+        dead_time: &DeadTimeParams,
+        channel_counts: &BTreeMap<String, ChannelCounts>,
+        extra_columns: &ExtraColumns,
+        metas: &BTreeMap<String, FlatMeta>,
+        header: &ExportHeader,
     ) {
         let mut content = String::new();
-        {
-            content.push_str("path\tvoltage\tcount_rate\tcounts\teffective_time\n");
+        if dead_time.enabled() {
+            content.push_str(
+                "path\tvoltage\tcount_rate\tcount_rate_corrected\tcounts\teffective_time",
+            );
+        } else {
+            content.push_str("path\tvoltage\tcount_rate\tcounts\teffective_time");
         }
+        content.push_str(&format!("{}\n", extra_columns.header()));
 
-        for (name, cache) in state_sorted.iter() {
-            if let PointState {
-                counts: Some(counts),
-                preprocess: Some(preprocess),
-                ..
-            } = cache
-            {
-                let effective_time = if processing_params.post_process.cut_bad_blocks {
-                    preprocess.effective_time() as f32 * 1e-9
-                } else {
-                    preprocess.acquisition_time as f32 * 1e-9
-                };
+        let cut_bad_blocks = processing_params.post_process.cut_bad_blocks;
 
-                let count_rate = *counts as f32 / effective_time;
+        for (name, cache) in state_sorted.iter() {
+            if let (
+                PointState {
+                    counts: Some(counts),
+                    preprocess: Some(preprocess),
+                    ..
+                },
+                Some((count_rate, count_rate_corrected)),
+            ) = (
+                cache,
+                point_rates(
+                    cache,
+                    channel_counts.get(name.as_str()),
+                    cut_bad_blocks,
+                    dead_time,
+                    &header.time_window,
+                    &header.event_filter,
+                ),
+            ) {
+                let effective_time = filtered_time(
+                    preprocess,
+                    cut_bad_blocks,
+                    &header.time_window,
+                    &header.event_filter,
+                );
 
                 let point_name = {
                     let temp = PathBuf::from(name);
                     temp.file_name().unwrap().to_owned()
                 };
 
+                let (rates, effective_time) = if dead_time.enabled() {
+                    (
+                        format!("{count_rate}\t{count_rate_corrected}"),
+                        effective_time.to_string(),
+                    )
+                } else {
+                    let (count_rate, effective_time) = legacy_rate(*counts, effective_time);
+                    (format!("{count_rate}"), effective_time.to_string())
+                };
+
                 content.push_str(&format!(
-                    "{point_name:?}\t{}\t{count_rate}\t{counts}\t{effective_time}\n",
-                    preprocess.hv
+                    "{point_name:?}\t{}\t{rates}\t{counts}\t{effective_time}{}\n",
+                    preprocess.hv,
+                    extra_columns.values(
+                        metas.get(*name),
+                        cache,
+                        channel_counts.get(name.as_str()),
+                    )
                 ));
             }
         }
 
-        DataViewerApp::save_text_file(save_folder, "PPV", Some("tsv"), &content);
+        let parts = NameParts::table("PPV", "ppv", &header.sources);
+        DataViewerApp::save_text_file(sink, &parts, Some("tsv"), header, &content);
     }
 
     /// Isomorphic way to save currentry opened files in [PlotMode::PPT] mode
@@ -525,34 +1660,62 @@ impl DataViewerApp {
     /// Result will be saved in `PPT.tsv` file in a place according [DataViewerApp::save_text_file]
     ///
     /// # Arguments
-    /// * `save_folder` - Directory where the file should be saved (on wasm side can be any).
+    /// * `sink` - Destination of the exported file.
     /// * `state_sorted` - A ref copy of [DataViewerApp::state] converted to vec (must be sorted for pretty results).
     /// * `processing_params` - A ref copy of [ViewerState] to get processing parameters.
+    /// * `dead_time` - Dead-time correction, if enabled `count_rate_corrected` column is added.
+    /// * `extra_columns` - Optional columns appended to each row.
+    /// * `metas` - Flattened metas of the points (source of the meta columns).
+    /// * `header` - Provenance header prepended to the file.
     ///
+    #[allow(clippy::too_many_arguments)]
     fn files_save_ppt(
-        save_folder: &Path,
+        sink: &mut ExportSink,
         state_sorted: &Vec<(&String, &PointState)>,
         processing_params: &ViewerState,
+        dead_time: &DeadTimeParams,
+        channel_counts: &BTreeMap<String, ChannelCounts>,
+        extra_columns: &ExtraColumns,
+        metas: &BTreeMap<String, FlatMeta>,
+        header: &ExportHeader,
     ) {
         let mut content = String::new();
-        {
-            content.push_str("path\ttime\ttime_raw\tcount_rate\tcounts\teffective_time\n");
+        if dead_time.enabled() {
+            content.push_str(
+                "path\ttime\ttime_raw\tcount_rate\tcount_rate_corrected\tcounts\teffective_time",
+            );
+        } else {
+            content.push_str("path\ttime\ttime_raw\tcount_rate\tcounts\teffective_time");
         }
+        content.push_str(&format!("{}\n", extra_columns.header()));
 
-        for (name, cache) in state_sorted.iter() {
-            if let PointState {
-                counts: Some(counts),
-                preprocess: Some(preprocess),
-                ..
-            } = cache
-            {
-                let effective_time = if processing_params.post_process.cut_bad_blocks {
-                    preprocess.effective_time() as f32 * 1e-9
-                } else {
-                    preprocess.acquisition_time as f32 * 1e-9
-                };
+        let cut_bad_blocks = processing_params.post_process.cut_bad_blocks;
 
-                let count_rate = *counts as f32 / effective_time;
+        for (name, cache) in state_sorted.iter() {
+            if let (
+                PointState {
+                    counts: Some(counts),
+                    preprocess: Some(preprocess),
+                    ..
+                },
+                Some((count_rate, count_rate_corrected)),
+            ) = (
+                cache,
+                point_rates(
+                    cache,
+                    channel_counts.get(name.as_str()),
+                    cut_bad_blocks,
+                    dead_time,
+                    &header.time_window,
+                    &header.event_filter,
+                ),
+            ) {
+                let effective_time = filtered_time(
+                    preprocess,
+                    cut_bad_blocks,
+                    &header.time_window,
+                    &header.event_filter,
+                );
 
                 let point_name = {
                     let temp = PathBuf::from(name);
@@ -561,14 +1724,30 @@ impl DataViewerApp {
 
                 let start_time = preprocess.start_time;
 
+                let (rates, effective_time) = if dead_time.enabled() {
+                    (
+                        format!("{count_rate}\t{count_rate_corrected}"),
+                        effective_time.to_string(),
+                    )
+                } else {
+                    let (count_rate, effective_time) = legacy_rate(*counts, effective_time);
+                    (format!("{count_rate}"), effective_time.to_string())
+                };
+
                 content.push_str(&format!(
-                    "{point_name:?}\t{start_time:?}\t{}\t{count_rate}\t{counts}\t{effective_time}\n",
-                    start_time.and_utc().timestamp()
+                    "{point_name:?}\t{start_time:?}\t{}\t{rates}\t{counts}\t{effective_time}{}\n",
+                    start_time.and_utc().timestamp(),
+                    extra_columns.values(
+                        metas.get(*name),
+                        cache,
+                        channel_counts.get(name.as_str()),
+                    )
                 ));
             }
         }
 
-        DataViewerApp::save_text_file(save_folder, "PPT", Some("tsv"), &content);
+        let parts = NameParts::table("PPT", "ppt", &header.sources);
+        DataViewerApp::save_text_file(sink, &parts, Some("tsv"), header, &content);
     }
 
     /// Isomorphic way to save currentry opened files in [PlotMode::Histogram] mode
@@ -580,20 +1759,26 @@ impl DataViewerApp {
     /// - For data structure see [PointHistogram::to_csv]
     ///
     /// # Arguments
-    /// * `save_folder` - Directory where the file should be saved (on wasm side can be any).
+    /// * `sink` - Destination of the exported files.
     /// * `state` - A ref copy of [DataViewerApp::state] converted to vec.
+    /// * `header` - Provenance header (per point files get it with only their own source).
     ///
-    fn files_save_histograms(save_folder: &Path, state: &Vec<(&String, &PointState)>) {
+    fn files_save_histograms(
+        sink: &mut ExportSink,
+        state: &Vec<(&String, &PointState)>,
+        header: &ExportHeader,
+    ) {
         let opened_hists = state
             .iter()
             .filter_map(|(name, cache)| {
                 if let PointState {
                     opened: true,
                     histogram: Some(histogram),
+                    preprocess,
                     ..
                 } = cache
                 {
-                    Some((name, histogram))
+                    Some((name, histogram, preprocess))
                 } else {
                     None
                 }
@@ -601,62 +1786,179 @@ impl DataViewerApp {
             .collect::<Vec<_>>();
 
         // Save each hist into separate file
-        for (name, histogram) in &opened_hists {
+        for (name, histogram, preprocess) in &opened_hists {
             let data = histogram.to_csv('\t');
-            DataViewerApp::save_text_file(save_folder, name, Some("tsv"), &data);
+            let parts = NameParts::point(name, "hist", preprocess.as_ref());
+            DataViewerApp::save_text_file(
+                sink,
+                &parts,
+                Some("tsv"),
+                &header.for_source(name),
+                &data,
+            );
         }
 
         // Save merged histogram
         let merged_hist = PointHistogram::new_merged(
             &opened_hists
                 .into_iter()
-                .map(|(_, hist)| hist)
+                .map(|(_, hist, _)| hist)
                 .collect::<Vec<_>>(),
         );
         let merged_data = merged_hist.to_csv('\t');
-        DataViewerApp::save_text_file(save_folder, "merged", Some("tsv"), &merged_data);
+        let parts = NameParts {
+            name: "merged".to_owned(),
+            mode: "hist".to_owned(),
+            ..Default::default()
+        };
+        DataViewerApp::save_text_file(sink, &parts, Some("tsv"), header, &merged_data);
     }
 
     /// Isomorphic text file save
     ///
-    /// - Native: method will save file to `$save_folder/$name` via fs
-    /// - WASM: method will download file via browser
-    ///
-    /// Method will try to extract set name (parent folder) and run name (parent of parent folder).
-    /// On success filename will be like `{run_name}-{set_name}-{name}.{pref_ext}`. On failure it will use just `{name}`.
+    /// File is written to the `sink` (see [ExportSink] for where it ends up and how collisions are handled).
+    /// Filename is rendered from `parts` with the sink [FileNaming] template
+    /// (`{run_name}-{set_name}-{name}.{pref_ext}` by default).
     ///
     /// # Arguments
     ///
-    /// * `save_folder` - Directory where the file should be saved (on wasm side can be any).
-    /// * `name` - Desired filename (preferred without extension).
+    /// * `sink` - Destination of the exported file.
+    /// * `parts` - Values for the filename template.
     /// * `pref_ext` - Optional desired file extension (if None - nothing will be added).
+    /// * `header` - Provenance header, written as commented lines before the content.
     /// * `content` - Text file content.
     ///
-    fn save_text_file(save_folder: &Path, name: &str, pref_ext: Option<&str>, content: &str) {
-        #[cfg(target_arch = "wasm32")]
-        let _ = save_folder;
-        #[cfg(target_arch = "wasm32")]
-        let save_folder = PathBuf::new(); // ensure correctness
+    fn save_text_file(
+        sink: &mut ExportSink,
+        parts: &NameParts,
+        pref_ext: Option<&str>,
+        header: &ExportHeader,
+        content: &str,
+    ) {
+        let content = header.to_text() + content;
+        DataViewerApp::save_binary_file(sink, parts, pref_ext, content.as_bytes());
+    }
 
-        let filename = construct_filename(name, pref_ext);
+    /// Isomorphic binary file save
+    ///
+    /// Same as [DataViewerApp::save_text_file] but for binary content (no header is added).
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - Destination of the exported file.
+    /// * `parts` - Values for the filename template.
+    /// * `pref_ext` - Optional desired file extension (if None - nothing will be added).
+    /// * `content` - File content.
+    ///
+    fn save_binary_file(
+        sink: &mut ExportSink,
+        parts: &NameParts,
+        pref_ext: Option<&str>,
+        content: &[u8],
+    ) {
+        sink.write(parts, pref_ext, content);
+    }
 
-        let mut filepath = PathBuf::from(save_folder);
-        filepath.push(filename);
+    fn process(&mut self) {
+        self.process_points(false)
+    }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            std::fs::write(filepath, content).unwrap();
+    /// Runs coincidence analysis of the `points` in background (see [DataViewerApp::coincidences]).
+    ///
+    /// Events are taken from the [EventsCache], points missing in the cache are processed (and cached).
+    fn compute_coincidences(&self, points: Vec<String>) {
+        if points.is_empty() {
+            return;
         }
+
+        let params = self.processing_params.clone();
+        let time_window = self.time_window;
+        let filter = self.event_filter.clone();
+        let process_hash = params_hash(&params, &time_window, &filter).process;
+        let mut coincidences = Coincidences::new(self.coincidences.params.clone());
+        let result = Arc::clone(&self.coincidences.result);
+        let progress = Arc::clone(&self.coincidences.progress);
+        let events_cache = Arc::clone(&self.events_cache);
         #[cfg(target_arch = "wasm32")]
-        download(filepath.to_str().unwrap(), content);
+        let processor_pool = Arc::clone(&self.processor_pool);
+
+        *progress.lock() = Some((0, points.len()));
+
+        spawn(async move {
+            for (idx, point) in points.iter().enumerate() {
+                let cached = events_cache.lock().get(point, process_hash);
+                let events = match cached {
+                    Some(events) => Some(events),
+                    None => {
+                        #[cfg(not(target_arch = "wasm32"))]
+                        let events = process_point_events(
+                            PathBuf::from(point),
+                            params.process.clone(),
+                            params.post_process,
+                            time_window,
+                        )
+                        .await
+                        .flatten();
+                        #[cfg(target_arch = "wasm32")]
+                        let events = worker_pool::run(
+                            Arc::clone(&processor_pool),
+                            (
+                                PathBuf::from(point),
+                                params.process.clone(),
+                                params.post_process,
+                                time_window,
+                                params.histogram.clone(),
+                                filter.clone(),
+                                true,
+                            ),
+                        )
+                        .await
+                        .and_then(|(_, events)| events);
+
+                        let events = events.map(Arc::new);
+                        if let Some(events) = &events {
+                            events_cache.lock().insert(
+                                point.clone(),
+                                process_hash,
+                                Arc::clone(events),
+                            );
+                        }
+                        events
+                    }
+                };
+
+                if let Some(events) = events {
+                    let effective_time = filtered_time(
+                        &events.preprocess,
+                        params.post_process.cut_bad_blocks,
+                        &time_window,
+                        &filter,
+                    );
+                    coincidences.add_point(&filter.apply(events.events.clone()), effective_time);
+                }
+                *progress.lock() = Some((idx + 1, points.len()));
+            }
+
+            *result.lock() = Some(coincidences);
+            *progress.lock() = None;
+        });
     }
 
-    fn process(&mut self) {
-        let changed = self.processing_params.changed;
+    /// Processes opened points.
+    ///
+    /// Points which are up to date (processed with current parameters and not modified on disk)
+    /// are skipped unless `force` is set. If only histogram parameters were changed histograms are
+    /// rebuilt from the [EventsCache] (when the point events are still cached).
+    fn process_points(&mut self, force: bool) {
         self.processing_params.changed = false;
 
         let params = self.processing_params.clone();
+        let time_window = self.time_window;
+        let filter = self.event_filter.clone();
+        let params_hash = params_hash(&params, &time_window, &filter);
         let state = Arc::clone(&self.state);
+        let records = Arc::clone(&self.records);
+        let events_cache = Arc::clone(&self.events_cache);
         let status = Arc::clone(&self.processing_status);
 
         let files_to_processed = {
@@ -684,61 +1986,171 @@ impl DataViewerApp {
             status.processed = 0;
             status.running = true
         }
+        self.processing_log.lock().start(&files_to_processed);
 
         for filepath in files_to_processed {
             let configuration_local = state.clone();
+            let records = Arc::clone(&records);
+            let events_cache = Arc::clone(&events_cache);
             let status = Arc::clone(&status);
+            let log = Arc::clone(&self.processing_log);
 
-            // get random worker from pool
             #[cfg(target_arch = "wasm32")]
-            let mut point_processor = {
-                let concurrency = self.processor_pool.len();
-                let worker_num =
-                    js_sys::eval(format!("Math.floor( Math.random() * {concurrency})").as_str())
-                        .unwrap()
-                        .as_f64()
-                        .unwrap() as usize;
-                self.processor_pool[worker_num].fork()
-            };
+            let processor_pool = Arc::clone(&self.processor_pool);
+            #[cfg(target_arch = "wasm32")]
+            let keep_events = events_cache.lock().enabled();
 
             let processing = params.clone();
+            let filter = filter.clone();
             spawn(async move {
-                let modified =
-                    processing::storage::load_modified_time(filepath.clone().into()).await;
+                log.lock().set_stage(&filepath, FileStage::Loading);
+                let modified = crate::load_modified_time(filepath.clone().into()).await;
                 if let Some(modified) = modified {
+                    records
+                        .lock()
+                        .disk_modified
+                        .insert(filepath.clone(), modified);
+
+                    let up_to_date = records.lock().records.get(&filepath)
+                        == Some(&ProcessingRecord {
+                            params_hash,
+                            error: false,
+                        });
+
                     let conf: egui::mutex::MutexGuard<'_, BTreeMap<String, PointState>> =
                         configuration_local.lock();
                     if let Some(&PointState {
                         modified: Some(modified_2),
+                        histogram: Some(_),
                         ..
                     }) = conf.get(&filepath)
                     {
-                        if !changed && modified <= modified_2 {
+                        if !force && up_to_date && modified <= modified_2 {
+                            log.lock().set_stage(&filepath, FileStage::Cached);
                             crate::inc_status(status);
                             return;
                         }
                     }
                 }
 
-                #[cfg(not(target_arch = "wasm32"))]
-                let point_state = process_point(
-                    filepath.clone().into(),
-                    processing.process,
-                    processing.post_process,
-                    processing.histogram,
-                )
-                .await;
-                #[cfg(target_arch = "wasm32")]
-                let point_state = point_processor
-                    .run((
-                        filepath.clone().into(),
-                        processing.process,
-                        processing.post_process,
-                        processing.histogram,
-                    ))
+                // histogram-only changes are applied to the cached events
+                let cached = if force {
+                    None
+                } else {
+                    events_cache
+                        .lock()
+                        .get(&filepath, params_hash.process)
+                        .filter(|cached| match (modified, cached.modified) {
+                            (Some(disk), Some(processed)) => disk <= processed,
+                            _ => false,
+                        })
+                };
+
+                let from_cache = cached.is_some();
+                let point_state = if let Some(cached) = cached {
+                    Some(
+                        Arc::unwrap_or_clone(cached)
+                            .into_point_state(processing.histogram, &filter),
+                    )
+                } else {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let events = async {
+                        let local =
+                            crate::modified_point_path(filepath.clone().into(), modified).await?;
+                        log.lock().set_stage(&filepath, FileStage::Processing);
+                        process_local_point_events(
+                            local,
+                            modified,
+                            processing.process,
+                            processing.post_process,
+                            time_window,
+                        )
+                        .await
+                    }
+                    .await
+                    .map(|events| {
+                        events.map_or((EMPTY_POINT, Default::default(), None), |events| {
+                            let (point_state, channel_counts) = events
+                                .clone()
+                                .into_point_state(processing.histogram, &filter);
+                            (point_state, channel_counts, Some(events))
+                        })
+                    });
+                    // point is loaded and processed by the worker, waiting for it is not loading
+                    #[cfg(target_arch = "wasm32")]
+                    log.lock().set_stage(&filepath, FileStage::Queued);
+                    #[cfg(target_arch = "wasm32")]
+                    let events = worker_pool::run_notify(
+                        processor_pool,
+                        (
+                            filepath.clone().into(),
+                            processing.process,
+                            processing.post_process,
+                            time_window,
+                            processing.histogram,
+                            filter,
+                            keep_events,
+                        ),
+                        || {
+                            let mut log = log.lock();
+                            log.set_stage(&filepath, FileStage::Loading);
+                            log.set_stage(&filepath, FileStage::Processing);
+                        },
+                    )
                     .await;
 
-                let point_state = point_state.unwrap_or(EMPTY_POINT);
+                    events.map(|(point_state, channel_counts, events)| {
+                        if let Some(events) = events {
+                            events_cache.lock().insert(
+                                filepath.clone(),
+                                params_hash.process,
+                                Arc::new(events),
+                            );
+                        }
+                        (point_state, channel_counts)
+                    })
+                };
+
+                // points without events are processed successfully (not an error)
+                let failed = point_state.is_none();
+                log.lock().set_stage(
+                    &filepath,
+                    match (from_cache, failed) {
+                        (true, _) => FileStage::Cached,
+                        (false, true) => FileStage::Failed,
+                        (false, false) => FileStage::Done,
+                    },
+                );
+
+                let (point_state, channel_counts) = point_state.unzip();
+                {
+                    let mut records = records.lock();
+                    records.records.insert(
+                        filepath.clone(),
+                        ProcessingRecord {
+                            params_hash,
+                            error: failed,
+                        },
+                    );
+                    if let Some(channel_counts) = channel_counts {
+                        records
+                            .channel_counts
+                            .insert(filepath.clone(), channel_counts);
+                    } else {
+                        records.channel_counts.remove(&filepath);
+                    }
+                }
+
+                // failed points are unchecked, points without events stay checked with zero counts
+                let point_state = match point_state {
+                    Some(point_state) if point_state.histogram.is_none() => PointState {
+                        opened: true,
+                        counts: Some(0),
+                        ..point_state
+                    },
+                    Some(point_state) => point_state,
+                    None => EMPTY_POINT,
+                };
 
                 let mut conf: egui::mutex::MutexGuard<'_, BTreeMap<String, PointState>> =
                     configuration_local.lock();
@@ -760,26 +2172,56 @@ impl Default for DataViewerApp {
 
         Self {
             #[cfg(not(target_arch = "wasm32"))]
-            root: Arc::new(tokio::sync::Mutex::new(None)),
+            roots: Arc::new(tokio::sync::Mutex::new(vec![])),
             #[cfg(target_arch = "wasm32")]
-            root: Arc::new(std::sync::Mutex::new(None)),
+            roots: Arc::new(std::sync::Mutex::new(vec![])),
+            roots_history: Arc::new(Mutex::new(RootsHistory::load())),
             select_single: false,
-            name_contains: "".to_string(),
+            file_filter: FileFilter::default(),
+            meta_filter: MetaFilter::default(),
+            tree_selection: TreeSelection::default(),
+            annotations: Arc::new(Mutex::new(Annotations::default())),
+            tag_filter: TagFilter::default(),
+            table_view: false,
+            point_table: PointTable::default(),
+            point_metas: Arc::new(Mutex::new(BTreeMap::new())),
             state,
+            records: Arc::new(Mutex::new(ProcessingRecords {
+                current_hash: params_hash(
+                    &ViewerState::default(),
+                    &TimeWindow::default(),
+                    &EventFilter::default(),
+                ),
+                ..Default::default()
+            })),
+            events_cache: Arc::new(Mutex::new(EventsCache::default())),
+            memory: MemoryBudget::default(),
             current_path: None,
             processing_status,
+            processing_log: Arc::new(Mutex::new(ProcessingLog::default())),
+            export_status: Arc::new(Mutex::new(ProcessingStatus {
+                running: false,
+                total: 0,
+                processed: 0,
+            })),
             processing_params: ViewerState::default(),
+            dead_time: DeadTimeParams::default(),
+            time_window: TimeWindow::default(),
+            event_filter: EventFilter::default(),
+            coincidences: CoincidenceView::default(),
+            export_zip: false,
+            naming: FileNaming::default(),
+            export_refused: Arc::new(Mutex::new(vec![])),
+            extra_columns: ExtraColumns::default(),
+            meta_keys: Arc::new(Mutex::new(BTreeSet::new())),
+            loaded_header: Arc::new(Mutex::new(None)),
             plot_mode: PlotMode::Histogram,
+            #[cfg(not(target_arch = "wasm32"))]
+            root_jobs: Arc::new(Mutex::new(ConversionQueue::default())),
             #[cfg(target_arch = "wasm32")]
-            processor_pool: {
-                let concurrency =
-                    gloo::utils::window().navigator().hardware_concurrency() as usize - 1;
-                (0..concurrency)
-                    .collect::<std::vec::Vec<usize>>()
-                    .into_iter()
-                    .map(|_| PointProcessor::spawner().spawn("./worker.js"))
-                    .collect::<Vec<_>>()
-            },
+            processor_pool: WorkerPool::shared(
+                gloo::utils::window().navigator().hardware_concurrency() as usize - 1,
+            ),
         }
     }
 }
@@ -798,8 +2240,11 @@ impl eframe::App for DataViewerApp {
             self.files_editor(ui);
         });
 
+        let mut coincidences_requested = None;
+
         egui::CentralPanel::default().show(ctx, |ui| {
             let state = self.state.lock();
+            let records = self.records.lock();
 
             let thickness = if ctx.style().visuals.dark_mode {
                 1.0
@@ -810,6 +2255,8 @@ impl eframe::App for DataViewerApp {
             let mut left_border = 0.0;
             let mut right_border = 0.0;
 
+            let cut_bad_blocks = self.processing_params.post_process.cut_bad_blocks;
+
             let opened_files = state
                 .iter()
                 .filter(|(_, cache)| cache.opened)
@@ -831,6 +2278,9 @@ impl eframe::App for DataViewerApp {
                         .height(height - 35.0);
 
                     plot.show(ui, |plot_ui| {
+                        self.time_window.legend(plot_ui);
+                        self.event_filter.legend(plot_ui);
+
                         let bounds = plot_ui.plot_bounds();
                         left_border = bounds.min()[0] as f32;
                         right_border = bounds.max()[0] as f32;
@@ -872,33 +2322,26 @@ impl eframe::App for DataViewerApp {
                         .height(height - 35.0);
 
                     plot.show(ui, |plot_ui| {
+                        self.time_window.legend(plot_ui);
+                        self.event_filter.legend(plot_ui);
+
                         let points = opened_files
                             .iter()
-                            .filter_map(|(_, cache)| {
-                                if let PointState {
-                                    counts: Some(counts),
-                                    preprocess: Some(preprocess),
-                                    ..
-                                } = cache
-                                {
-                                    if self.processing_params.post_process.cut_bad_blocks {
-                                        Some([
-                                            preprocess.start_time.and_utc().timestamp_millis()
-                                                as f64,
-                                            *counts as f64
-                                                / (preprocess.effective_time() as f64 * 1e-9),
-                                        ])
-                                    } else {
-                                        Some([
-                                            preprocess.start_time.and_utc().timestamp_millis()
-                                                as f64,
-                                            *counts as f64
-                                                / (preprocess.acquisition_time as f64 * 1e-9),
-                                        ])
-                                    }
-                                } else {
-                                    None
-                                }
+                            .filter_map(|(name, cache)| {
+                                let (_, rate) = point_rates(
+                                    cache,
+                                    records.channel_counts.get(name.as_str()),
+                                    cut_bad_blocks,
+                                    &self.dead_time,
+                                    &self.time_window,
+                                    &self.event_filter,
+                                )?;
+                                cache.preprocess.as_ref().map(|preprocess| {
+                                    [
+                                        preprocess.start_time.and_utc().timestamp_millis() as f64,
+                                        rate,
+                                    ]
+                                })
                             })
                             .collect::<Vec<_>>();
 
@@ -911,31 +2354,24 @@ impl eframe::App for DataViewerApp {
                         .height(height - 35.0);
 
                     plot.show(ui, |plot_ui| {
+                        self.time_window.legend(plot_ui);
+                        self.event_filter.legend(plot_ui);
+
                         let points = opened_files
                             .iter()
-                            .filter_map(|(_, cache)| {
-                                if let PointState {
-                                    counts: Some(counts),
-                                    preprocess: Some(preprocess),
-                                    ..
-                                } = cache
-                                {
-                                    if self.processing_params.post_process.cut_bad_blocks {
-                                        Some([
-                                            preprocess.hv as f64,
-                                            *counts as f64
-                                                / (preprocess.effective_time() as f64 * 1e-9),
-                                        ])
-                                    } else {
-                                        Some([
-                                            preprocess.hv as f64,
-                                            *counts as f64
-                                                / (preprocess.acquisition_time as f64 * 1e-9),
-                                        ])
-                                    }
-                                } else {
-                                    None
-                                }
+                            .filter_map(|(name, cache)| {
+                                let (_, rate) = point_rates(
+                                    cache,
+                                    records.channel_counts.get(name.as_str()),
+                                    cut_bad_blocks,
+                                    &self.dead_time,
+                                    &self.time_window,
+                                    &self.event_filter,
+                                )?;
+                                cache
+                                    .preprocess
+                                    .as_ref()
+                                    .map(|preprocess| [preprocess.hv as f64, rate])
                             })
                             .collect::<Vec<_>>();
 
@@ -946,42 +2382,23 @@ impl eframe::App for DataViewerApp {
                                 let clicked_file = opened_files
                                     .iter()
                                     .filter_map(|(path, cache)| {
-                                        if let PointState {
-                                            counts: Some(counts),
-                                            preprocess: Some(preprocess),
-                                            ..
-                                        } = cache
-                                        {
-                                            // TODO: deduplicate this code
-                                            let point_pos = if self
-                                                .processing_params
-                                                .post_process
-                                                .cut_bad_blocks
-                                            {
-                                                PlotPoint::new(
-                                                    preprocess.hv,
-                                                    *counts as f64
-                                                        / preprocess.effective_time() as f64
-                                                        * 1e-9,
-                                                )
-                                            } else {
-                                                PlotPoint::new(
-                                                    preprocess.hv,
-                                                    *counts as f64
-                                                        / preprocess.acquisition_time as f64
-                                                        * 1e-9,
-                                                )
-                                            };
-
-                                            let distance =
-                                                point_pos.to_pos2().distance(pos.to_pos2());
-                                            if distance < 1e5 {
-                                                return Some((path, distance));
-                                            }
-                                            None
-                                        } else {
-                                            None
+                                        let (_, rate) = point_rates(
+                                            cache,
+                                            records.channel_counts.get(path.as_str()),
+                                            cut_bad_blocks,
+                                            &self.dead_time,
+                                            &self.time_window,
+                                            &self.event_filter,
+                                        )?;
+                                        let preprocess = cache.preprocess.as_ref()?;
+
+                                        let point_pos = PlotPoint::new(preprocess.hv, rate);
+
+                                        let distance = point_pos.to_pos2().distance(pos.to_pos2());
+                                        if distance < 1e5 {
+                                            return Some((path, distance));
                                         }
+                                        None
                                     })
                                     .min_by_key(|(_, distance)| (distance * 1000.0) as i64);
 
@@ -1003,27 +2420,45 @@ impl eframe::App for DataViewerApp {
                             }
                         }
 
-                        if let Some(current) = &self.current_path {
-                            if let PointState {
-                                counts: Some(counts),
-                                preprocess:
-                                    Some(Preprocess {
-                                        acquisition_time,
-                                        hv,
-                                        ..
-                                    }),
-                                ..
-                            } = state[current]
-                            {
-                                plot_ui.hline(
-                                    HLine::new("selection", counts as f64 / (acquisition_time as f64 * 1e-9))
-                                        .color(Color32::WHITE),
-                                );
-                                plot_ui.vline(VLine::new("selection", hv).color(Color32::WHITE));
+                        // selected point may be already closed
+                        if let Some((current, cache)) = self
+                            .current_path
+                            .as_ref()
+                            .and_then(|current| state.get_key_value(current))
+                        {
+                            if let (
+                                Some((_, rate)),
+                                PointState {
+                                    preprocess: Some(Preprocess { hv, .. }),
+                                    ..
+                                },
+                            ) = (
+                                point_rates(
+                                    cache,
+                                    records.channel_counts.get(current),
+                                    cut_bad_blocks,
+                                    &self.dead_time,
+                                    &self.time_window,
+                                    &self.event_filter,
+                                ),
+                                cache,
+                            ) {
+                                plot_ui.hline(HLine::new("selection", rate).color(Color32::WHITE));
+                                plot_ui.vline(VLine::new("selection", *hv).color(Color32::WHITE));
                             }
                         }
                     });
                 }
+                PlotMode::Coincidences => {
+                    if self.coincidences.show(ui, height) {
+                        coincidences_requested = Some(
+                            opened_files
+                                .iter()
+                                .map(|(name, _)| (*name).clone())
+                                .collect::<Vec<_>>(),
+                        );
+                    }
+                }
             }
 
             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
@@ -1059,27 +2494,23 @@ impl eframe::App for DataViewerApp {
 
                     #[cfg(not(target_arch = "wasm32"))]
                     {
-                        let mut command = tokio::process::Command::new("filtered-viewer");
-
-                        command
-                            .arg(filepath)
-                            .arg("--process")
-                            .arg(serde_json::to_string(&self.processing_params.process).unwrap())
-                            .arg("--postprocess")
-                            .arg(
-                                serde_json::to_string(&self.processing_params.post_process)
-                                    .unwrap(),
-                            );
+                        let mut args = vec![
+                            "--process".to_owned(),
+                            serde_json::to_string(&self.processing_params.process).unwrap(),
+                            "--postprocess".to_owned(),
+                            serde_json::to_string(&self.processing_params.post_process).unwrap(),
+                        ];
 
                         if self.plot_mode == PlotMode::Histogram {
-                            command
-                                .arg("--min")
-                                .arg(left_border.max(0.0).to_string())
-                                .arg("--max")
-                                .arg(right_border.max(0.0).to_string());
+                            args.extend([
+                                "--min".to_owned(),
+                                left_border.max(0.0).to_string(),
+                                "--max".to_owned(),
+                                right_border.max(0.0).to_string(),
+                            ]);
                         };
 
-                        command.spawn().unwrap();
+                        spawn_viewer("filtered-viewer", filepath, args);
                     }
                     #[cfg(target_arch = "wasm32")]
                     {
@@ -1119,12 +2550,7 @@ impl eframe::App for DataViewerApp {
                 if point_viewer_button.clicked() {
                     let filepath = marked_point.unwrap();
                     #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        tokio::process::Command::new("point-viewer")
-                            .arg(filepath)
-                            .spawn()
-                            .unwrap();
-                    }
+                    spawn_viewer("point-viewer", filepath, vec![]);
                     #[cfg(target_arch = "wasm32")]
                     {
                         let search = serde_qs::to_string(&ViewerMode::Waveforms {
@@ -1160,12 +2586,7 @@ impl eframe::App for DataViewerApp {
                 if trigger_viewer_button.clicked() {
                     let filepath = marked_point.unwrap();
                     #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        tokio::process::Command::new("trigger-viewer")
-                            .arg(filepath)
-                            .spawn()
-                            .unwrap();
-                    }
+                    spawn_viewer("trigger-viewer", filepath, vec![]);
                     #[cfg(target_arch = "wasm32")]
                     {
                         let search = serde_qs::to_string(&ViewerMode::Triggers {
@@ -1201,19 +2622,16 @@ impl eframe::App for DataViewerApp {
                 if bundle_viewer_button.clicked() {
                     let filepath = marked_point.unwrap();
                     #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        tokio::process::Command::new("bundle-viewer")
-                            .arg(filepath)
-                            .arg("--process")
-                            .arg(serde_json::to_string(&self.processing_params.process).unwrap())
-                            .arg("--postprocess")
-                            .arg(
-                                serde_json::to_string(&self.processing_params.post_process)
-                                    .unwrap(),
-                            )
-                            .spawn()
-                            .unwrap();
-                    }
+                    spawn_viewer(
+                        "bundle-viewer",
+                        filepath,
+                        vec![
+                            "--process".to_owned(),
+                            serde_json::to_string(&self.processing_params.process).unwrap(),
+                            "--postprocess".to_owned(),
+                            serde_json::to_string(&self.processing_params.post_process).unwrap(),
+                        ],
+                    );
                     #[cfg(target_arch = "wasm32")]
                     {
                         let search = serde_qs::to_string(&ViewerMode::Bundles {
@@ -1229,10 +2647,19 @@ impl eframe::App for DataViewerApp {
                     }
                 }
 
+                if let Some(point) = marked_point.map(PathBuf::from) {
+                    self.point_notes_button(ui, &point);
+                }
+
                 ui.radio_value(&mut self.plot_mode, PlotMode::Histogram, "Hist");
                 ui.radio_value(&mut self.plot_mode, PlotMode::PPT, "PPT");
                 ui.radio_value(&mut self.plot_mode, PlotMode::PPV, "PPV");
+                ui.radio_value(&mut self.plot_mode, PlotMode::Coincidences, "Coinc");
             });
         });
+
+        if let Some(points) = coincidences_requested {
+            self.compute_coincidences(points);
+        }
     }
 }
@@ -23,6 +23,9 @@ async fn main() -> eframe::Result<()> {
         directory: Option<PathBuf>,
         #[clap(long)]
         cache_directory: Option<String>,
+        /// numass-server address (e.g. http://localhost:8085) to browse and process points through its HTTP API
+        #[clap(long)]
+        server: Option<String>,
     }
 
     // abort programm if any of threads panic
@@ -44,8 +47,11 @@ async fn main() -> eframe::Result<()> {
         Box::new(|ctx| {
             install_image_loaders(&ctx.egui_ctx);
             let app = app::DataViewerApp::default();
-            if let Some(directory) = opt.directory {
-                *app.root.try_lock().unwrap() = Some(FSRepr::new(directory))
+            if let Some(server) = opt.server {
+                viewers::remote::connect(&server, opt.cache_directory.map(PathBuf::from));
+                app.open_root(opt.directory);
+            } else if let Some(directory) = opt.directory {
+                app.roots.try_lock().unwrap().push(FSRepr::new(directory))
             }
             Ok(Box::new(app))
         }),
@@ -17,6 +17,8 @@ use {
     std::sync::Arc,
     std::time::SystemTime,
     tokio::spawn,
+    viewers::file_filter::FileFilter,
+    viewers::tree_selection::{ClickAction, TreeSelection},
 };
 
 #[cfg(target_family = "unix")]
@@ -166,8 +168,10 @@ pub struct FaradeyViewerApp {
 
     select_single: bool,
 
-    /// Фильтр по имени файла (прячет файлы, не содержащие подстроки в имени в виджете файлового дерева)
-    name_contains: String,
+    /// Фильтр файлового дерева (прячет файлы, не подходящие под glob шаблоны)
+    file_filter: FileFilter,
+    /// Keyboard focus and range selection of the file tree.
+    tree_selection: TreeSelection,
 
     plot_mode: PlotMode,
     state: Arc<Mutex<BTreeMap<String, FaradeyPointState>>>,
@@ -226,21 +230,14 @@ impl FaradeyViewerApp {
             }
         };
 
-        let mut needs_to_be_marked = false;
+        let bulk_action = ui
+            .horizontal(|ui| {
+                ui.checkbox(&mut self.select_single, "select single");
+                self.tree_selection.input(ui)
+            })
+            .inner;
 
-        ui.checkbox(&mut self.select_single, "select single");
-
-        ui.horizontal(|ui| {
-            ui.label("name contains:");
-            ui.add_sized(
-                [100.0, 20.0],
-                egui::TextEdit::singleline(&mut self.name_contains),
-            );
-            needs_to_be_marked = ui
-                .button("+")
-                .on_hover_text("Выделить все видимые файлы")
-                .clicked();
-        });
+        let needs_to_be_marked = self.file_filter.input(ui);
 
         ui.horizontal(|ui| {
             self.files_open_button(ui);
@@ -254,48 +251,63 @@ impl FaradeyViewerApp {
             }
         });
 
-        egui::containers::ScrollArea::new([false, true]).show(ui, |ui| {
-            if let Some(root) = &mut root_copy {
-                let mut state_after = FileTreeState {
-                    need_load: false,
-                    need_process: false,
-                };
+        if let Some(root) = &mut root_copy {
+            let mut state_after = FileTreeState {
+                need_load: false,
+                need_process: false,
+            };
+
+            self.tree_selection.begin_frame();
 
+            egui::containers::ScrollArea::new([false, true]).show(ui, |ui| {
                 FaradeyViewerApp::file_tree_entry(
                     ui,
                     root,
                     &self.select_single,
-                    &self.name_contains,
+                    &self.file_filter,
+                    &mut self.tree_selection,
                     needs_to_be_marked,
                     &mut self.state.lock(),
                     &mut state_after,
                 );
+            });
 
-                if state_after.need_process && self.select_single {
-                    self.process();
-                }
+            let toggled = self.tree_selection.keyboard(ui.ctx());
+            state_after.need_process |= self.tree_selection.apply(
+                &mut self.state.lock(),
+                toggled,
+                bulk_action,
+                self.select_single,
+                EMPTY_FARADEY_POINT,
+                |cache| &mut cache.opened,
+            );
 
-                if state_after.need_load {
-                    let root_out = Arc::clone(&self.root);
-                    let mut root = root.clone();
+            if state_after.need_process && self.select_single {
+                self.process();
+            }
 
-                    spawn(async move {
-                        root.expand_reccurently().await;
-                        if let Ok(mut out) = root_out.try_lock() {
-                            out.replace(root);
-                        }
-                    });
-                }
+            if state_after.need_load {
+                let root_out = Arc::clone(&self.root);
+                let mut root = root.clone();
+
+                spawn(async move {
+                    root.expand_reccurently().await;
+                    if let Ok(mut out) = root_out.try_lock() {
+                        out.replace(root);
+                    }
+                });
             }
-        });
+        }
     }
 
     /// Recursive file tree drawer with logic embedded
+    #[allow(clippy::too_many_arguments)]
     fn file_tree_entry(
         ui: &mut egui::Ui,
         entry: &mut FSRepr,
         select_single: &bool,
-        name_contains: &str,
+        file_filter: &FileFilter,
+        selection: &mut TreeSelection,
         needs_to_be_marked: bool,
         opened_files: &mut BTreeMap<String, FaradeyPointState>,
         state_after: &mut FileTreeState,
@@ -303,12 +315,13 @@ impl FaradeyViewerApp {
         match entry {
             FSRepr::File { path, .. } => {
                 let key = path.to_str().unwrap().to_string();
-                if name_contains.is_empty() || key.contains(name_contains) {
+                if file_filter.matches(&key) {
                     let cache = opened_files
                         .entry(key.clone())
                         .or_insert(EMPTY_FARADEY_POINT);
                     let mut change_set = None;
                     let mut exclusive_point = None;
+                    let mut range = None;
 
                     ui.horizontal(|ui| {
                         if needs_to_be_marked {
@@ -316,20 +329,34 @@ impl FaradeyViewerApp {
                         }
 
                         if ui.checkbox(&mut cache.opened, "").changed() {
-                            if cache.opened && *select_single {
-                                exclusive_point = Some(key)
+                            match selection.clicked(&key, ui.input(|i| i.modifiers)) {
+                                ClickAction::Single => {
+                                    if cache.opened && *select_single {
+                                        exclusive_point = Some(key.clone())
+                                    }
+
+                                    if path.ends_with("meta") || path.ends_with("meta.df") {
+                                        change_set = Some(cache.opened)
+                                    };
+                                }
+                                ClickAction::Add => {}
+                                ClickAction::Range(keys) => range = Some((keys, cache.opened)),
                             }
-
-                            if path.ends_with("meta") || path.ends_with("meta.df") {
-                                change_set = Some(cache.opened)
-                            };
                         }
 
                         let filename = path.file_name().unwrap().to_str().unwrap();
-                        ui.label(filename);
+                        selection.label(ui, &key, filename);
                     });
 
-                    if let Some(point) = exclusive_point {
+                    if let Some((keys, opened)) = range {
+                        for key in keys {
+                            opened_files
+                                .entry(key)
+                                .or_insert(EMPTY_FARADEY_POINT)
+                                .opened = opened;
+                        }
+                        state_after.need_process = true;
+                    } else if let Some(point) = exclusive_point {
                         for (key, cache) in opened_files.iter_mut() {
                             if key != &point {
                                 cache.opened = false;
@@ -366,7 +393,8 @@ impl FaradeyViewerApp {
                                     ui,
                                     child,
                                     select_single,
-                                    name_contains,
+                                    file_filter,
+                                    selection,
                                     needs_to_be_marked,
                                     opened_files,
                                     state_after,
@@ -427,7 +455,8 @@ impl Default for FaradeyViewerApp {
         Self {
             root: Arc::new(tokio::sync::Mutex::new(None)),
             select_single: false,
-            name_contains: "".to_string(),
+            file_filter: FileFilter::default(),
+            tree_selection: TreeSelection::default(),
             state,
             plot_mode: PlotMode::Lines,
         }
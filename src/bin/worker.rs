@@ -7,5 +7,6 @@ fn main() {
     use gloo::worker::Registrable;
     use viewers::PointProcessor;
     console_error_panic_hook::set_once();
+    viewers::worker_pool::watch_worker_errors();
     PointProcessor::registrar().register();
 }
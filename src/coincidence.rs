@@ -0,0 +1,369 @@
+//! Coincidence analysis of the events in different detector channels.
+//!
+//! Two events of different channels are coincident if they are closer than the coincidence window.
+//! Every coincident pair is counted (an event can take part in several pairs).
+use std::{collections::BTreeMap, sync::Arc};
+
+use egui::{mutex::Mutex, Color32, Ui};
+use egui_plot::{Legend, Line, Plot};
+use processing::types::{FrameEvent, NumassEvents};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoincidenceParams {
+    /// Coincidence window (in ns).
+    pub window: u64,
+    /// Number of bins of the time difference distribution.
+    pub dt_bins: usize,
+    pub amplitude_min: f32,
+    pub amplitude_max: f32,
+    /// Number of bins of the summed amplitude spectrum.
+    pub amplitude_bins: usize,
+}
+
+impl Default for CoincidenceParams {
+    fn default() -> Self {
+        Self {
+            window: 1000,
+            dt_bins: 100,
+            amplitude_min: 0.0,
+            amplitude_max: 80.0,
+            amplitude_bins: 400,
+        }
+    }
+}
+
+/// Index of the `value` bin in `bins` equal bins of `[min, max]` (None if out of range).
+fn bin_index(value: f64, min: f64, max: f64, bins: usize) -> Option<usize> {
+    if bins == 0 || max <= min || value < min || value > max {
+        return None;
+    }
+    Some(((((value - min) / (max - min)) * bins as f64) as usize).min(bins - 1))
+}
+
+/// Accumulated coincidences of the analysed points.
+#[derive(Debug, Clone)]
+pub struct Coincidences {
+    pub params: CoincidenceParams,
+    pub points: usize,
+    /// Total effective time of the points (in s).
+    pub time: f64,
+    /// Number of events per channel.
+    pub singles: BTreeMap<u8, usize>,
+    /// Number of coincident pairs per channel pair (lower channel first).
+    pub pairs: BTreeMap<(u8, u8), usize>,
+    /// Distribution of the time difference `t(higher channel) - t(lower channel)` over `[-window, window]`.
+    pub dt: Vec<usize>,
+    /// Spectrum of the summed amplitudes of coincident pairs.
+    pub amplitude: Vec<usize>,
+}
+
+impl Coincidences {
+    pub fn new(params: CoincidenceParams) -> Self {
+        Self {
+            dt: vec![0; params.dt_bins],
+            amplitude: vec![0; params.amplitude_bins],
+            params,
+            points: 0,
+            time: 0.0,
+            singles: BTreeMap::new(),
+            pairs: BTreeMap::new(),
+        }
+    }
+
+    /// Adds coincidences of a point.
+    ///
+    /// # Arguments
+    /// * `events` - processed (and filtered) events of the point.
+    /// * `effective_time` - point effective time (in s).
+    ///
+    pub fn add_point(&mut self, events: &NumassEvents, effective_time: f64) {
+        let mut flat = vec![];
+        for (time, frame) in events {
+            for (offset, event) in frame {
+                if let FrameEvent::Event {
+                    channel, amplitude, ..
+                } = event
+                {
+                    flat.push((time + *offset as u64, *channel, *amplitude));
+                }
+            }
+        }
+        flat.sort_unstable_by_key(|(time, _, _)| *time);
+
+        let window = self.params.window;
+        for (idx, &(time_1, channel_1, amplitude_1)) in flat.iter().enumerate() {
+            *self.singles.entry(channel_1).or_default() += 1;
+
+            for &(time_2, channel_2, amplitude_2) in flat[idx + 1..]
+                .iter()
+                .take_while(|(time_2, _, _)| time_2 - time_1 <= window)
+            {
+                if channel_1 == channel_2 {
+                    continue;
+                }
+                let delta = (time_2 - time_1) as f64;
+                let (pair, dt) = if channel_1 < channel_2 {
+                    ((channel_1, channel_2), delta)
+                } else {
+                    ((channel_2, channel_1), -delta)
+                };
+                *self.pairs.entry(pair).or_default() += 1;
+
+                if let Some(idx) = bin_index(dt, -(window as f64), window as f64, self.dt.len()) {
+                    self.dt[idx] += 1;
+                }
+                if let Some(idx) = bin_index(
+                    (amplitude_1 + amplitude_2) as f64,
+                    self.params.amplitude_min as f64,
+                    self.params.amplitude_max as f64,
+                    self.amplitude.len(),
+                ) {
+                    self.amplitude[idx] += 1;
+                }
+            }
+        }
+
+        self.points += 1;
+        self.time += effective_time;
+    }
+
+    /// Coincidence rate (in Hz) of the channel pair.
+    pub fn rate(&self, channel_1: u8, channel_2: u8) -> f64 {
+        let pair = (channel_1.min(channel_2), channel_1.max(channel_2));
+        let count = self.pairs.get(&pair).copied().unwrap_or_default();
+        if self.time > 0.0 {
+            count as f64 / self.time
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Coincidence analysis panel of the data-viewer.
+#[derive(Default)]
+pub struct CoincidenceView {
+    pub params: CoincidenceParams,
+    /// Result of the last analysis.
+    pub result: Arc<Mutex<Option<Coincidences>>>,
+    /// Analysed and total number of points of the running analysis.
+    pub progress: Arc<Mutex<Option<(usize, usize)>>>,
+}
+
+impl CoincidenceView {
+    fn matrix(ui: &mut Ui, result: &Coincidences) {
+        let channels = result.singles.keys().copied().collect::<Vec<_>>();
+        let max_rate = result
+            .pairs
+            .keys()
+            .map(|(channel_1, channel_2)| result.rate(*channel_1, *channel_2))
+            .fold(0.0, f64::max);
+
+        egui::Grid::new("coincidence_matrix")
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Hz");
+                for channel in &channels {
+                    ui.label(format!("#{}", channel + 1));
+                }
+                ui.end_row();
+
+                for channel_1 in &channels {
+                    ui.label(format!("#{}", channel_1 + 1));
+                    for channel_2 in &channels {
+                        if channel_1 == channel_2 {
+                            ui.label("-");
+                            continue;
+                        }
+                        let rate = result.rate(*channel_1, *channel_2);
+                        let intensity = if max_rate > 0.0 {
+                            (rate / max_rate) as f32
+                        } else {
+                            0.0
+                        };
+                        ui.colored_label(
+                            Color32::from_rgb(
+                                (120.0 + 135.0 * intensity) as u8,
+                                (120.0 + 80.0 * (1.0 - intensity)) as u8,
+                                120,
+                            ),
+                            format!("{rate:.3}"),
+                        )
+                        .on_hover_text(format!(
+                            "#{} & #{}: {} пар",
+                            channel_1 + 1,
+                            channel_2 + 1,
+                            result
+                                .pairs
+                                .get(&(*channel_1.min(channel_2), *channel_1.max(channel_2)))
+                                .copied()
+                                .unwrap_or_default()
+                        ));
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Draws analysis parameters, coincidence matrix and distributions.
+    ///
+    /// Returns true if analysis of the opened points was requested.
+    pub fn show(&mut self, ui: &mut Ui, height: f32) -> bool {
+        let mut compute = false;
+
+        ui.horizontal(|ui| {
+            ui.label("window:");
+            ui.add(
+                egui::DragValue::new(&mut self.params.window)
+                    .range(1..=1_000_000)
+                    .suffix(" ns"),
+            );
+            ui.label("dt bins:");
+            ui.add(egui::DragValue::new(&mut self.params.dt_bins).range(1..=10_000));
+            ui.label("sum amplitude:");
+            ui.add(
+                egui::DragValue::new(&mut self.params.amplitude_min)
+                    .range(f32::MIN..=self.params.amplitude_max)
+                    .speed(0.1),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.params.amplitude_max)
+                    .range(self.params.amplitude_min..=f32::MAX)
+                    .speed(0.1),
+            );
+            ui.label("bins:");
+            ui.add(egui::DragValue::new(&mut self.params.amplitude_bins).range(1..=10_000));
+
+            let progress = *self.progress.lock();
+            if let Some((processed, total)) = progress {
+                ui.add(
+                    egui::ProgressBar::new(processed as f32 / total.max(1) as f32)
+                        .desired_width(120.0)
+                        .text(format!("{processed}/{total}")),
+                );
+            } else if ui
+                .button("compute")
+                .on_hover_text("Найти совпадения в открытых точках")
+                .clicked()
+            {
+                compute = true;
+            }
+        });
+
+        let guard = self.result.lock();
+        let Some(result) = guard.as_ref() else {
+            ui.label("no results yet");
+            return compute;
+        };
+
+        ui.label(format!(
+            "{} points, {:.1} s, window {} ns",
+            result.points, result.time, result.params.window
+        ));
+        Self::matrix(ui, result);
+
+        let window = result.params.window as f64;
+        let dt_step = 2.0 * window / result.dt.len().max(1) as f64;
+        let dt = result
+            .dt
+            .iter()
+            .enumerate()
+            .map(|(idx, count)| [-window + (idx as f64 + 0.5) * dt_step, *count as f64])
+            .collect::<Vec<_>>();
+
+        let amplitude_min = result.params.amplitude_min as f64;
+        let amplitude_step = (result.params.amplitude_max as f64 - amplitude_min)
+            / result.amplitude.len().max(1) as f64;
+        let amplitude = result
+            .amplitude
+            .iter()
+            .enumerate()
+            .map(|(idx, count)| {
+                [
+                    amplitude_min + (idx as f64 + 0.5) * amplitude_step,
+                    *count as f64,
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let plot_height = (height - ui.min_rect().height() - 80.0).max(100.0);
+        ui.columns(2, |columns| {
+            Plot::new("coincidence_dt")
+                .legend(Legend::default())
+                .height(plot_height)
+                .show(&mut columns[0], |plot_ui| {
+                    plot_ui.line(Line::new("dt, ns", dt));
+                });
+            Plot::new("coincidence_amplitude")
+                .legend(Legend::default())
+                .height(plot_height)
+                .show(&mut columns[1], |plot_ui| {
+                    plot_ui.line(Line::new("summed amplitude", amplitude));
+                });
+        });
+
+        compute
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(channel: u8, amplitude: f32) -> FrameEvent {
+        FrameEvent::Event {
+            channel,
+            amplitude,
+            size: 10,
+        }
+    }
+
+    #[test]
+    fn pairs_inside_window() {
+        let events = BTreeMap::from([
+            // pair of #1 and #2
+            (0, vec![(0, event(0, 10.0)), (50, event(1, 20.0))]),
+            // same channel, not a pair
+            (1000, vec![(0, event(2, 5.0)), (30, event(2, 5.0))]),
+            // outside of the window
+            (2000, vec![(0, event(1, 1.0))]),
+            (2150, vec![(0, event(0, 1.0))]),
+            // pair of #2 and #4 (higher channel first), reset is ignored
+            (
+                3000,
+                vec![
+                    (0, event(3, 10.0)),
+                    (10, FrameEvent::Reset { size: 10 }),
+                    (20, event(1, 10.0)),
+                ],
+            ),
+        ]);
+
+        let mut coincidences = Coincidences::new(CoincidenceParams {
+            window: 100,
+            dt_bins: 2,
+            amplitude_min: 0.0,
+            amplitude_max: 80.0,
+            amplitude_bins: 8,
+        });
+        coincidences.add_point(&events, 10.0);
+
+        assert_eq!(
+            coincidences.pairs,
+            BTreeMap::from([((0, 1), 1), ((1, 3), 1)])
+        );
+        assert_eq!(
+            coincidences.singles,
+            BTreeMap::from([(0, 2), (1, 3), (2, 2), (3, 1)])
+        );
+        // +50 ns for #1-#2 and -20 ns for #2-#4
+        assert_eq!(coincidences.dt, vec![1, 1]);
+        assert_eq!(coincidences.amplitude, vec![0, 0, 1, 1, 0, 0, 0, 0]);
+        assert_eq!(coincidences.rate(1, 0), 0.1);
+        assert_eq!(coincidences.rate(0, 2), 0.0);
+
+        coincidences.add_point(&events, 10.0);
+        assert_eq!(coincidences.points, 2);
+        assert_eq!(coincidences.rate(0, 1), 0.1);
+    }
+}
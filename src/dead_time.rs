@@ -0,0 +1,287 @@
+//! Dead-time (pile-up) corrections for measured count rates.
+//!
+//! Corrections are applied per channel to the rate computed from the point histogram
+//! and summed afterwards, since each channel has its own electronics dead time.
+//! The correction factor of a channel is taken from all its events, not only from the histogrammed ones.
+use std::collections::BTreeMap;
+
+use egui::Ui;
+use processing::histogram::PointHistogram;
+use serde::{Deserialize, Serialize};
+
+use crate::events_cache::ChannelCounts;
+
+/// Dead-time model used to restore the true rate from the measured one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DeadTimeModel {
+    /// No correction, measured rate is displayed as is.
+    #[default]
+    None,
+    /// `n = m / (1 - m * tau)`
+    NonParalyzable,
+    /// `m = n * exp(-n * tau)` (solved for `n` on the low-rate branch)
+    Paralyzable,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadTimeParams {
+    pub model: DeadTimeModel,
+    /// Dead time (in ns) for channels without an explicit value.
+    pub default_tau: f32,
+    /// Per channel dead time (in ns), key is a zero-based channel id.
+    pub tau: BTreeMap<u8, f32>,
+}
+
+impl Default for DeadTimeParams {
+    fn default() -> Self {
+        Self {
+            model: DeadTimeModel::None,
+            default_tau: 500.0,
+            tau: BTreeMap::new(),
+        }
+    }
+}
+
+impl DeadTimeParams {
+    /// Returns true if the correction changes rates.
+    pub fn enabled(&self) -> bool {
+        self.model != DeadTimeModel::None
+    }
+
+    /// Dead time for channel in seconds.
+    pub fn tau_s(&self, channel: u8) -> f64 {
+        *self.tau.get(&channel).unwrap_or(&self.default_tau) as f64 * 1e-9
+    }
+
+    /// Restores true rate (in Hz) from the measured `rate` (in Hz) of the `channel`.
+    pub fn correct(&self, channel: u8, rate: f64) -> f64 {
+        let tau = self.tau_s(channel);
+        if tau <= 0.0 || rate <= 0.0 {
+            return rate;
+        }
+
+        match self.model {
+            DeadTimeModel::None => rate,
+            DeadTimeModel::NonParalyzable => {
+                let loss = 1.0 - rate * tau;
+                if loss > 0.0 {
+                    rate / loss
+                } else {
+                    f64::INFINITY
+                }
+            }
+            DeadTimeModel::Paralyzable => {
+                // measured rate can not exceed 1/(e*tau), return the saturation point
+                if rate * tau >= (-1.0f64).exp() {
+                    return 1.0 / tau;
+                }
+                // m = n*exp(-n*tau) is concave and increasing on [0, 1/tau],
+                // so Newton iterations from n = m converge monotonically from below
+                let mut n = rate;
+                for _ in 0..64 {
+                    let exp = (-n * tau).exp();
+                    let step = (n * exp - rate) / ((1.0 - n * tau) * exp);
+                    n -= step;
+                    if step.abs() <= n * 1e-12 {
+                        break;
+                    }
+                }
+                n
+            }
+        }
+    }
+
+    /// Corrects `rate` (in Hz) of the part of the `channel` events (passed the histogram range
+    /// and event filters).
+    ///
+    /// Dead time is caused by all events of the channel, so the correction factor is taken from
+    /// the channel `total_rate` (in Hz, before any cuts) and applied to the `rate`.
+    pub fn correct_part(&self, channel: u8, rate: f64, total_rate: f64) -> f64 {
+        if total_rate <= 0.0 {
+            return self.correct(channel, rate);
+        }
+        rate * self.correct(channel, total_rate) / total_rate
+    }
+
+    /// Corrected total rate (in Hz) of the point.
+    ///
+    /// Rate is calculated separately for each histogram channel, corrected and summed
+    /// (see [DeadTimeParams::correct_part]).
+    ///
+    /// # Arguments
+    /// * `histogram` - point histogram (source of per channel counts).
+    /// * `effective_time` - effective time of the histogram counts in seconds.
+    /// * `channel_counts` - per channel counts before cuts (histogram counts are used if None).
+    /// * `counts_time` - effective time of the `channel_counts` in seconds.
+    ///
+    pub fn corrected_rate(
+        &self,
+        histogram: &PointHistogram,
+        effective_time: f64,
+        channel_counts: Option<&ChannelCounts>,
+        counts_time: f64,
+    ) -> f64 {
+        histogram
+            .channels
+            .iter()
+            .map(|(channel, bins)| {
+                let rate = bins.iter().sum::<f32>() as f64 / effective_time;
+                let total_rate = match channel_counts {
+                    Some(counts) if counts_time > 0.0 => {
+                        counts.get(channel).copied().unwrap_or_default() as f64 / counts_time
+                    }
+                    _ => rate,
+                };
+                self.correct_part(*channel, rate, total_rate)
+            })
+            .sum()
+    }
+
+    /// Draws dead-time correction editor. Returns updated parameters.
+    pub fn input(&self, ui: &mut Ui, _ctx: &egui::Context) -> Self {
+        let mut params = self.clone();
+
+        egui::ComboBox::from_label("dead time")
+            .selected_text(format!("{:?}", params.model))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut params.model, DeadTimeModel::None, "None");
+                ui.selectable_value(
+                    &mut params.model,
+                    DeadTimeModel::NonParalyzable,
+                    "NonParalyzable",
+                );
+                ui.selectable_value(&mut params.model, DeadTimeModel::Paralyzable, "Paralyzable");
+            });
+
+        if params.enabled() {
+            ui.horizontal(|ui| {
+                ui.label("tau (default):");
+                ui.add(
+                    egui::DragValue::new(&mut params.default_tau)
+                        .range(0.0..=1e6)
+                        .suffix(" ns"),
+                );
+            });
+
+            let mut remove = None;
+            for (channel, tau) in params.tau.iter_mut() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("tau ch #{}:", channel + 1));
+                    ui.add(egui::DragValue::new(tau).range(0.0..=1e6).suffix(" ns"));
+                    if ui.button("x").clicked() {
+                        remove = Some(*channel);
+                    }
+                });
+            }
+            if let Some(channel) = remove {
+                params.tau.remove(&channel);
+            }
+
+            if ui
+                .button("+ channel")
+                .on_hover_text("Задать dead time для отдельного канала")
+                .clicked()
+            {
+                let channel = match params.tau.keys().next_back() {
+                    Some(last) => last.checked_add(1),
+                    None => Some(0),
+                };
+                if let Some(channel) = channel {
+                    params.tau.insert(channel, params.default_tau);
+                }
+            }
+        }
+
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(model: DeadTimeModel, tau: f32) -> DeadTimeParams {
+        DeadTimeParams {
+            model,
+            default_tau: tau,
+            tau: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn paralyzable_converges() {
+        let params = params(DeadTimeModel::Paralyzable, 500.0);
+        let tau = params.tau_s(0);
+        for true_rate in [1.0, 1e3, 1e5, 1e6, 1.9e6] {
+            let measured = true_rate * (-true_rate * tau).exp();
+            let restored = params.correct(0, measured);
+            assert!(
+                ((restored - true_rate) / true_rate).abs() < 1e-9,
+                "{true_rate} restored as {restored}"
+            );
+        }
+    }
+
+    #[test]
+    fn non_paralyzable_inverts() {
+        let params = params(DeadTimeModel::NonParalyzable, 500.0);
+        let tau = params.tau_s(0);
+        let true_rate = 1e5;
+        let measured = true_rate / (1.0 + true_rate * tau);
+        assert!((params.correct(0, measured) - true_rate).abs() < 1e-6);
+    }
+
+    #[test]
+    fn zero_tau_keeps_rate() {
+        for model in [
+            DeadTimeModel::None,
+            DeadTimeModel::NonParalyzable,
+            DeadTimeModel::Paralyzable,
+        ] {
+            assert_eq!(params(model, 0.0).correct(0, 1234.5), 1234.5);
+        }
+    }
+
+    #[test]
+    fn beyond_maximum() {
+        let params_p = params(DeadTimeModel::Paralyzable, 500.0);
+        let tau = params_p.tau_s(0);
+        // maximal measured rate of the paralyzable model is 1/(e*tau)
+        let max = (-1.0f64).exp() / tau;
+        assert_eq!(params_p.correct(0, 1.01 * max), 1.0 / tau);
+        assert_eq!(params_p.correct(0, 2.0 * max), 1.0 / tau);
+
+        let params_n = params(DeadTimeModel::NonParalyzable, 500.0);
+        assert_eq!(params_n.correct(0, 2.0 / tau), f64::INFINITY);
+    }
+
+    #[test]
+    fn part_uses_total_rate() {
+        let params = params(DeadTimeModel::Paralyzable, 500.0);
+        let tau = params.tau_s(0);
+        // only 1% of the events pass the cuts
+        let true_total = 5e5;
+        let measured_total = true_total * (-true_total * tau).exp();
+        let measured_part = measured_total * 0.01;
+
+        let corrected = params.correct_part(0, measured_part, measured_total);
+        assert!((corrected / (true_total * 0.01) - 1.0).abs() < 1e-9);
+        // correcting the part alone underestimates the losses
+        assert!(params.correct(0, measured_part) < 0.8 * corrected);
+    }
+
+    #[test]
+    fn part_without_total_rate() {
+        let params = params(DeadTimeModel::NonParalyzable, 500.0);
+        assert_eq!(params.correct_part(0, 1e5, 0.0), params.correct(0, 1e5));
+        assert_eq!(params.correct_part(0, 0.0, 1e5), 0.0);
+    }
+
+    #[test]
+    fn per_channel_tau() {
+        let mut params = params(DeadTimeModel::NonParalyzable, 500.0);
+        params.tau.insert(2, 0.0);
+        assert_eq!(params.correct(2, 1e5), 1e5);
+        assert!(params.correct(0, 1e5) > 1e5);
+    }
+}
@@ -0,0 +1,385 @@
+//! Viewer-side filters of the processed events applied before histogramming.
+//!
+//! Filters are applied to the (cached) post processed events, so their changes do not require
+//! reprocessing of the points (see [EventsCache](crate::events_cache::EventsCache)).
+use std::collections::BTreeSet;
+
+use egui::Ui;
+use egui_plot::{Line, PlotUi};
+use processing::types::{FrameEvent, NumassEvents};
+use serde::{Deserialize, Serialize};
+
+/// Number of the detector channels shown in the channel mask editor.
+const CHANNELS_COUNT: u8 = 7;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventFilter {
+    /// Channels (zero-based ids) which events are dropped.
+    pub excluded_channels: BTreeSet<u8>,
+    pub time_enabled: bool,
+    /// Start of the time-in-point window (in seconds).
+    pub time_from: f64,
+    /// End of the time-in-point window (in seconds).
+    pub time_to: f64,
+    pub amplitude_enabled: bool,
+    pub amplitude_min: f32,
+    pub amplitude_max: f32,
+    pub multiplicity_enabled: bool,
+    /// Minimal number of events in a frame (before other filters are applied).
+    pub multiplicity_min: usize,
+    /// Maximal number of events in a frame (before other filters are applied).
+    pub multiplicity_max: usize,
+    /// Events closer than this to a reset or an overflow are dropped (in ns, 0 - disabled).
+    pub reset_window: u64,
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        Self {
+            excluded_channels: BTreeSet::new(),
+            time_enabled: false,
+            time_from: 0.0,
+            time_to: 100.0,
+            amplitude_enabled: false,
+            amplitude_min: 0.0,
+            amplitude_max: 40.0,
+            multiplicity_enabled: false,
+            multiplicity_min: 1,
+            multiplicity_max: 1,
+            reset_window: 0,
+        }
+    }
+}
+
+/// Checks if `time` is closer than `window` to any of the sorted `marks`.
+fn near_mark(marks: &[u64], time: u64, window: u64) -> bool {
+    let idx = marks.partition_point(|&mark| mark < time);
+    (idx < marks.len() && marks[idx] - time <= window)
+        || (idx > 0 && time - marks[idx - 1] <= window)
+}
+
+impl EventFilter {
+    /// Returns true if any filter is enabled.
+    pub fn is_active(&self) -> bool {
+        !self.excluded_channels.is_empty()
+            || self.time_enabled
+            || self.amplitude_enabled
+            || self.multiplicity_enabled
+            || self.reset_window > 0
+    }
+
+    /// Drops filtered out events (resets, overflows and frame markers are kept).
+    pub fn apply(&self, mut events: NumassEvents) -> NumassEvents {
+        if !self.is_active() {
+            return events;
+        }
+
+        let mut marks = vec![];
+        if self.reset_window > 0 {
+            for (time, frame) in &events {
+                for (offset, event) in frame {
+                    if matches!(
+                        event,
+                        FrameEvent::Reset { .. } | FrameEvent::Overflow { .. }
+                    ) {
+                        marks.push(time + *offset as u64);
+                    }
+                }
+            }
+            marks.sort_unstable();
+        }
+
+        for (time, frame) in events.iter_mut() {
+            let multiplicity = frame
+                .iter()
+                .filter(|(_, event)| matches!(event, FrameEvent::Event { .. }))
+                .count();
+            let multiplicity_passed = !self.multiplicity_enabled
+                || (self.multiplicity_min..=self.multiplicity_max).contains(&multiplicity);
+
+            frame.retain(|(offset, event)| {
+                let FrameEvent::Event {
+                    channel, amplitude, ..
+                } = event
+                else {
+                    return true;
+                };
+                let event_time = *time + *offset as u64;
+
+                multiplicity_passed
+                    && !self.excluded_channels.contains(channel)
+                    && (!self.time_enabled
+                        || (self.time_from..=self.time_to).contains(&(event_time as f64 * 1e-9)))
+                    && (!self.amplitude_enabled
+                        || (self.amplitude_min..=self.amplitude_max).contains(amplitude))
+                    && (self.reset_window == 0 || !near_mark(&marks, event_time, self.reset_window))
+            });
+        }
+
+        events
+    }
+
+    /// Short description of the enabled filters (None if no filters are enabled).
+    pub fn summary(&self) -> Option<String> {
+        let mut parts = vec![];
+        if !self.excluded_channels.is_empty() {
+            let channels = self
+                .excluded_channels
+                .iter()
+                .map(|channel| format!("#{}", channel + 1))
+                .collect::<Vec<_>>()
+                .join(",");
+            parts.push(format!("without {channels}"));
+        }
+        if self.time_enabled {
+            parts.push(format!("t {}..{} s", self.time_from, self.time_to));
+        }
+        if self.amplitude_enabled {
+            parts.push(format!("A {}..{}", self.amplitude_min, self.amplitude_max));
+        }
+        if self.multiplicity_enabled {
+            parts.push(format!(
+                "mult {}..{}",
+                self.multiplicity_min, self.multiplicity_max
+            ));
+        }
+        if self.reset_window > 0 {
+            parts.push(format!("reset ±{} ns", self.reset_window));
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+
+    /// Adds enabled filters description to the plot legend.
+    pub fn legend(&self, plot_ui: &mut PlotUi) {
+        if let Some(summary) = self.summary() {
+            plot_ui.line(Line::new(
+                format!("filters: {summary}"),
+                Vec::<[f64; 2]>::new(),
+            ));
+        }
+    }
+
+    /// Draws filters editor. Returns updated filters.
+    pub fn input(&self, ui: &mut Ui, _ctx: &egui::Context) -> Self {
+        let mut filter = self.clone();
+
+        egui::CollapsingHeader::new(match self.summary() {
+            Some(summary) => format!("event filters ({summary})"),
+            None => "event filters".to_owned(),
+        })
+        .id_salt("event_filter")
+        .show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("channels:");
+                for channel in 0..CHANNELS_COUNT {
+                    let mut enabled = !filter.excluded_channels.contains(&channel);
+                    if ui
+                        .checkbox(&mut enabled, format!("{}", channel + 1))
+                        .changed()
+                    {
+                        if enabled {
+                            filter.excluded_channels.remove(&channel);
+                        } else {
+                            filter.excluded_channels.insert(channel);
+                        }
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut filter.time_enabled, "time:");
+                ui.add_enabled_ui(filter.time_enabled, |ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut filter.time_from)
+                            .range(0.0..=filter.time_to)
+                            .suffix(" s"),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut filter.time_to)
+                            .range(filter.time_from..=1e6)
+                            .suffix(" s"),
+                    );
+                });
+            })
+            .response
+            .on_hover_text("Окно времени от начала точки");
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut filter.amplitude_enabled, "amplitude:");
+                ui.add_enabled_ui(filter.amplitude_enabled, |ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut filter.amplitude_min)
+                            .range(f32::MIN..=filter.amplitude_max)
+                            .speed(0.1),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut filter.amplitude_max)
+                            .range(filter.amplitude_min..=f32::MAX)
+                            .speed(0.1),
+                    );
+                });
+            });
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut filter.multiplicity_enabled, "multiplicity:");
+                ui.add_enabled_ui(filter.multiplicity_enabled, |ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut filter.multiplicity_min)
+                            .range(1..=filter.multiplicity_max),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut filter.multiplicity_max)
+                            .range(filter.multiplicity_min..=usize::MAX),
+                    );
+                });
+            })
+            .response
+            .on_hover_text("Количество событий в кадре");
+
+            ui.horizontal(|ui| {
+                ui.label("reset window:");
+                ui.add(
+                    egui::DragValue::new(&mut filter.reset_window)
+                        .range(0..=u64::MAX)
+                        .suffix(" ns"),
+                )
+                .on_hover_text(
+                    "Исключать события ближе заданного времени к сбросам и переполнениям (0 - не исключать)",
+                );
+            });
+        });
+
+        filter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn event(channel: u8, amplitude: f32) -> FrameEvent {
+        FrameEvent::Event {
+            channel,
+            amplitude,
+            size: 10,
+        }
+    }
+
+    /// Channels of the events left (in order).
+    fn channels(events: &NumassEvents) -> Vec<u8> {
+        events
+            .values()
+            .flatten()
+            .filter_map(|(_, event)| match event {
+                FrameEvent::Event { channel, .. } => Some(*channel),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn resets(events: &NumassEvents) -> usize {
+        events
+            .values()
+            .flatten()
+            .filter(|(_, event)| matches!(event, FrameEvent::Reset { .. }))
+            .count()
+    }
+
+    #[test]
+    fn near_mark_bounds() {
+        let marks = [100, 200];
+        assert!(near_mark(&marks, 100, 0));
+        assert!(near_mark(&marks, 95, 5));
+        assert!(!near_mark(&marks, 94, 5));
+        assert!(near_mark(&marks, 150, 50));
+        assert!(!near_mark(&marks, 149, 10));
+        assert!(near_mark(&marks, 209, 9));
+        assert!(!near_mark(&marks, 210, 9));
+        assert!(!near_mark(&[], 100, 1000));
+    }
+
+    #[test]
+    fn inactive_keeps_events() {
+        let events = BTreeMap::from([(0, vec![(0, event(0, 1.0)), (5, event(3, 50.0))])]);
+        assert_eq!(channels(&EventFilter::default().apply(events)), vec![0, 3]);
+    }
+
+    #[test]
+    fn channels_and_amplitude() {
+        let events = BTreeMap::from([(
+            0,
+            vec![
+                (0, event(0, 10.0)),
+                (1, event(1, 10.0)),
+                (2, event(2, 50.0)),
+                (3, FrameEvent::Reset { size: 10 }),
+            ],
+        )]);
+        let filter = EventFilter {
+            excluded_channels: BTreeSet::from([1]),
+            amplitude_enabled: true,
+            amplitude_min: 5.0,
+            amplitude_max: 40.0,
+            ..Default::default()
+        };
+        let events = filter.apply(events);
+        assert_eq!(channels(&events), vec![0]);
+        assert_eq!(resets(&events), 1);
+    }
+
+    #[test]
+    fn time_in_point() {
+        let events = BTreeMap::from([
+            (500_000_000, vec![(0, event(0, 10.0))]),
+            (1_500_000_000, vec![(0, event(1, 10.0))]),
+            (2_500_000_000, vec![(0, event(2, 10.0))]),
+        ]);
+        let filter = EventFilter {
+            time_enabled: true,
+            time_from: 1.0,
+            time_to: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(channels(&filter.apply(events)), vec![1]);
+    }
+
+    #[test]
+    fn multiplicity_counts_all_events() {
+        let events = BTreeMap::from([
+            (0, vec![(0, event(0, 10.0))]),
+            (1000, vec![(0, event(1, 10.0)), (5, event(2, 50.0))]),
+        ]);
+        // multiplicity is counted before the amplitude filter
+        let filter = EventFilter {
+            multiplicity_enabled: true,
+            multiplicity_min: 1,
+            multiplicity_max: 1,
+            amplitude_enabled: true,
+            amplitude_min: 0.0,
+            amplitude_max: 40.0,
+            ..Default::default()
+        };
+        assert_eq!(channels(&filter.apply(events)), vec![0]);
+    }
+
+    #[test]
+    fn reset_window() {
+        let events = BTreeMap::from([
+            (0, vec![(0, event(0, 10.0))]),
+            (1000, vec![(0, event(1, 10.0))]),
+            (1050, vec![(0, FrameEvent::Reset { size: 10 })]),
+            (2000, vec![(0, event(2, 10.0))]),
+        ]);
+        let filter = EventFilter {
+            reset_window: 100,
+            ..Default::default()
+        };
+        let events = filter.apply(events);
+        // resets of the other frames are taken into account too
+        assert_eq!(channels(&events), vec![0, 2]);
+        assert_eq!(resets(&events), 1);
+    }
+}
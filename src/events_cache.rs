@@ -0,0 +1,297 @@
+//! Processed events of the points kept for re-histogramming without reprocessing.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    mem::size_of_val,
+    sync::Arc,
+    time::SystemTime,
+};
+
+use processing::{
+    histogram::HistogramParams,
+    preprocess::Preprocess,
+    types::{FrameEvent, NumassEvents},
+    utils::events_to_histogram,
+    viewer::PointState,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::event_filter::EventFilter;
+
+/// Default size of the events cache (in bytes).
+#[cfg(not(target_arch = "wasm32"))]
+pub const DEFAULT_EVENTS_BUDGET: usize = 512 * 1024 * 1024;
+/// Default size of the events cache (in bytes).
+///
+/// On the web events are sent from the workers to the main thread only to be cached,
+/// so the cache is smaller (it can be disabled with zero budget, see [EventsCache::enabled]).
+#[cfg(target_arch = "wasm32")]
+pub const DEFAULT_EVENTS_BUDGET: usize = 128 * 1024 * 1024;
+
+/// Approximate overhead of a [NumassEvents] map entry (key + node share).
+const ENTRY_OVERHEAD: usize = 48;
+
+/// Number of events of each channel of a point before event filters (key is a zero-based channel id).
+///
+/// Dead time is caused by all events of a channel, so corrections use these counts
+/// (see [DeadTimeParams::corrected_rate](crate::dead_time::DeadTimeParams::corrected_rate)).
+pub type ChannelCounts = BTreeMap<u8, usize>;
+
+/// Processed (and post processed) events of a point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedEvents {
+    pub events: NumassEvents,
+    pub preprocess: Preprocess,
+    /// File modification time at the moment of processing.
+    pub modified: Option<SystemTime>,
+}
+
+impl CachedEvents {
+    /// Approximate memory usage (in bytes).
+    pub fn size(&self) -> usize {
+        self.events
+            .values()
+            .map(|frame| size_of_val(frame.as_slice()) + ENTRY_OVERHEAD)
+            .sum()
+    }
+
+    /// Number of events of each channel (event filters are not applied).
+    pub fn channel_counts(&self) -> ChannelCounts {
+        let mut counts = ChannelCounts::new();
+        for frame in self.events.values() {
+            for (_, event) in frame {
+                if let FrameEvent::Event { channel, .. } = event {
+                    *counts.entry(*channel).or_default() += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// Builds point state with histogram of the `filter`ed events.
+    ///
+    /// Returns the state and unfiltered [ChannelCounts] of the point.
+    pub fn into_point_state(
+        self,
+        histogram: HistogramParams,
+        filter: &EventFilter,
+    ) -> (PointState, ChannelCounts) {
+        let channel_counts = self.channel_counts();
+        let histogram = events_to_histogram(filter.apply(self.events), histogram);
+        let counts = Some(histogram.events_all(None));
+
+        let state = PointState {
+            opened: true,
+            histogram: Some(histogram),
+            preprocess: Some(self.preprocess),
+            modified: self.modified,
+            counts,
+        };
+        (state, channel_counts)
+    }
+}
+
+/// Value of the [EventsCache] with known memory usage.
+pub trait CacheSize {
+    /// Approximate memory usage (in bytes).
+    fn size(&self) -> usize;
+}
+
+impl CacheSize for CachedEvents {
+    fn size(&self) -> usize {
+        CachedEvents::size(self)
+    }
+}
+
+struct CacheEntry<T> {
+    /// Hash of process/post process parameters events were produced with.
+    process_hash: u64,
+    events: Arc<T>,
+    size: usize,
+}
+
+/// LRU cache of the [CachedEvents] limited by the memory budget.
+pub struct EventsCache<T = CachedEvents> {
+    /// Memory budget (in bytes).
+    pub budget: usize,
+    entries: BTreeMap<String, CacheEntry<T>>,
+    /// Keys from the least to the most recently used.
+    order: VecDeque<String>,
+    used: usize,
+}
+
+impl<T> Default for EventsCache<T> {
+    fn default() -> Self {
+        Self {
+            budget: DEFAULT_EVENTS_BUDGET,
+            entries: BTreeMap::new(),
+            order: VecDeque::new(),
+            used: 0,
+        }
+    }
+}
+
+impl<T: CacheSize> EventsCache<T> {
+    /// Memory used by the cached events (in bytes).
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Cache has non zero budget (processed events should be kept).
+    pub fn enabled(&self) -> bool {
+        self.budget > 0
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(idx) = self.order.iter().position(|cached| cached == key) {
+            let key = self.order.remove(idx).unwrap();
+            self.order.push_back(key);
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Arc<T>> {
+        let entry = self.entries.remove(key)?;
+        self.order.retain(|cached| cached != key);
+        self.used -= entry.size;
+        Some(entry.events)
+    }
+
+    /// Evicts least recently used entries until cache fits into budget.
+    pub fn shrink(&mut self) {
+        while self.used > self.budget {
+            let Some(key) = self.order.front().cloned() else {
+                break;
+            };
+            self.remove(&key);
+        }
+    }
+
+    /// Returns cached events of the point if they were produced with `process_hash` parameters.
+    pub fn get(&mut self, key: &str, process_hash: u64) -> Option<Arc<T>> {
+        let events = self
+            .entries
+            .get(key)
+            .filter(|entry| entry.process_hash == process_hash)
+            .map(|entry| Arc::clone(&entry.events))?;
+        self.touch(key);
+        Some(events)
+    }
+
+    /// Caches `events` (events larger than the whole budget are not cached).
+    pub fn insert(&mut self, key: String, process_hash: u64, events: Arc<T>) {
+        self.remove(&key);
+
+        let size = events.size();
+        if size > self.budget {
+            return;
+        }
+
+        self.used += size;
+        self.order.push_back(key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                process_hash,
+                events,
+                size,
+            },
+        );
+        self.shrink();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Item(usize);
+
+    impl CacheSize for Item {
+        fn size(&self) -> usize {
+            self.0
+        }
+    }
+
+    fn cache(budget: usize) -> EventsCache<Item> {
+        EventsCache {
+            budget,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = cache(30);
+        cache.insert("a".to_owned(), 0, Arc::new(Item(10)));
+        cache.insert("b".to_owned(), 0, Arc::new(Item(10)));
+        cache.insert("c".to_owned(), 0, Arc::new(Item(10)));
+        assert_eq!(cache.used(), 30);
+
+        // "a" becomes the most recently used
+        assert!(cache.get("a", 0).is_some());
+        cache.insert("d".to_owned(), 0, Arc::new(Item(10)));
+
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.used(), 30);
+        assert!(cache.get("b", 0).is_none());
+        assert!(cache.get("a", 0).is_some());
+        assert!(cache.get("c", 0).is_some());
+        assert!(cache.get("d", 0).is_some());
+    }
+
+    #[test]
+    fn get_checks_process_hash() {
+        let mut cache = cache(30);
+        cache.insert("a".to_owned(), 1, Arc::new(Item(10)));
+        assert!(cache.get("a", 2).is_none());
+        assert!(cache.get("a", 1).is_some());
+    }
+
+    #[test]
+    fn replace_and_remove_update_usage() {
+        let mut cache = cache(30);
+        cache.insert("a".to_owned(), 0, Arc::new(Item(10)));
+        cache.insert("a".to_owned(), 1, Arc::new(Item(20)));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.used(), 20);
+
+        assert!(cache.remove("a").is_some());
+        assert!(cache.remove("a").is_none());
+        assert!(cache.is_empty());
+        assert_eq!(cache.used(), 0);
+    }
+
+    #[test]
+    fn too_large_is_not_cached() {
+        let mut cache = cache(30);
+        cache.insert("a".to_owned(), 0, Arc::new(Item(10)));
+        cache.insert("b".to_owned(), 0, Arc::new(Item(31)));
+        assert!(cache.get("b", 0).is_none());
+        assert_eq!(cache.used(), 10);
+    }
+
+    #[test]
+    fn shrink_to_smaller_budget() {
+        let mut cache = cache(30);
+        for key in ["a", "b", "c"] {
+            cache.insert(key.to_owned(), 0, Arc::new(Item(10)));
+        }
+        cache.budget = 15;
+        cache.shrink();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.used(), 10);
+        assert!(cache.get("c", 0).is_some());
+
+        cache.budget = 0;
+        cache.shrink();
+        assert!(cache.is_empty());
+        assert!(!cache.enabled());
+    }
+}
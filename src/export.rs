@@ -0,0 +1,1011 @@
+//! Helpers shared by the data-viewer exporters.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+};
+
+use egui::Ui;
+
+use processing::{
+    histogram::HistogramParams,
+    numass::NumassMeta,
+    postprocess::PostProcessParams,
+    preprocess::Preprocess,
+    process::ProcessParams,
+    types::FrameEvent,
+    viewer::{PointState, ViewerState},
+};
+use serde::{Deserialize, Serialize};
+
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    dead_time::DeadTimeParams,
+    event_filter::EventFilter,
+    events_cache::{CachedEvents, ChannelCounts},
+    load_meta,
+    rates::filtered_time,
+    time_window::TimeWindow,
+};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+    fn download_bytes(filename: &str, bytes: &[u8]);
+}
+
+/// Export file naming settings (shared by all exporters).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileNaming {
+    /// File name template (without extension), see [FileNaming::PLACEHOLDERS].
+    pub template: String,
+    /// User defined name of the current parameters set (`{preset}` placeholder).
+    pub preset: String,
+}
+
+impl Default for FileNaming {
+    fn default() -> Self {
+        Self {
+            template: "{run}-{set}-{name}".to_owned(),
+            preset: String::new(),
+        }
+    }
+}
+
+/// Values substituted into [FileNaming::template].
+#[derive(Debug, Clone, Default)]
+pub struct NameParts {
+    /// Point file name or table name.
+    pub name: String,
+    /// Run folder name (parent of the set folder).
+    pub run: String,
+    /// Set folder name (parent of the point).
+    pub set: String,
+    /// Export kind (`hist`, `ppt`, `ppv`, `events`, `root`).
+    pub mode: String,
+    pub hv: Option<f32>,
+    /// Point start time (export time is used if not set).
+    pub date: Option<chrono::NaiveDateTime>,
+}
+
+impl NameParts {
+    /// Name parts of a point file export.
+    pub fn point(path: &str, mode: &str, preprocess: Option<&Preprocess>) -> Self {
+        let path = PathBuf::from(path);
+        let file_name = |path: Option<&Path>| {
+            path.and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        let set = path.parent();
+
+        Self {
+            name: file_name(Some(&path)),
+            set: file_name(set),
+            run: file_name(set.and_then(|set| set.parent())),
+            mode: mode.to_owned(),
+            hv: preprocess.map(|preprocess| preprocess.hv),
+            date: preprocess.map(|preprocess| preprocess.start_time),
+        }
+    }
+
+    /// Name parts of a table combining several points.
+    ///
+    /// Run and set are filled only if all sources share them.
+    pub fn table(name: &str, mode: &str, sources: &[(String, Option<String>)]) -> Self {
+        fn common(mut values: impl Iterator<Item = String>) -> String {
+            let first = values.next().unwrap_or_default();
+            if values.all(|value| value == first) {
+                first
+            } else {
+                String::new()
+            }
+        }
+
+        let points = sources
+            .iter()
+            .map(|(source, _)| NameParts::point(source, mode, None))
+            .collect::<Vec<_>>();
+
+        Self {
+            name: name.to_owned(),
+            run: common(points.iter().map(|point| point.run.clone())),
+            set: common(points.iter().map(|point| point.set.clone())),
+            mode: mode.to_owned(),
+            hv: None,
+            date: None,
+        }
+    }
+}
+
+impl FileNaming {
+    /// Supported placeholders with descriptions.
+    pub const PLACEHOLDERS: [(&'static str, &'static str); 7] = [
+        ("{run}", "run folder"),
+        ("{set}", "set folder"),
+        ("{name}", "point file or table name"),
+        ("{hv}", "point HV"),
+        ("{date}", "point start date (export date for tables)"),
+        ("{mode}", "hist/ppt/ppv/events/root"),
+        ("{preset}", "preset name"),
+    ];
+
+    /// Renders file name for `parts` with optional extension.
+    ///
+    /// Empty placeholders are dropped together with one adjacent separator (`-`, `_` or space)
+    /// and path separators are replaced, so the result is always a plain file name.
+    pub fn render(&self, parts: &NameParts, ext: Option<&str>) -> String {
+        const SEPARATORS: [char; 3] = ['-', '_', ' '];
+
+        let date = parts
+            .date
+            .unwrap_or_else(|| chrono::Local::now().naive_local())
+            .format("%Y%m%d")
+            .to_string();
+        let hv = parts.hv.map(|hv| hv.to_string()).unwrap_or_default();
+        let values = [
+            ("{run}", parts.run.as_str()),
+            ("{set}", parts.set.as_str()),
+            ("{name}", parts.name.as_str()),
+            ("{hv}", hv.as_str()),
+            ("{date}", date.as_str()),
+            ("{mode}", parts.mode.as_str()),
+            ("{preset}", self.preset.as_str()),
+        ];
+
+        let mut name = String::new();
+        // separator before the next literal part belongs to a dropped placeholder
+        let mut skip_separator = false;
+        let mut rest = self.template.as_str();
+        while !rest.is_empty() {
+            let placeholder = values
+                .iter()
+                .find(|(placeholder, _)| rest.starts_with(placeholder));
+            if let Some((placeholder, value)) = placeholder {
+                rest = &rest[placeholder.len()..];
+                if value.is_empty() {
+                    if name.ends_with(SEPARATORS) {
+                        name.pop();
+                    } else {
+                        skip_separator = true;
+                    }
+                } else {
+                    name.push_str(value);
+                    skip_separator = false;
+                }
+            } else {
+                let ch = rest.chars().next().unwrap();
+                rest = &rest[ch.len_utf8()..];
+                if !(skip_separator && SEPARATORS.contains(&ch)) {
+                    name.push(ch);
+                }
+                skip_separator = false;
+            }
+        }
+
+        let mut name = name.replace(['/', '\\', ':'], "_");
+        if name.is_empty() {
+            name = parts.name.clone();
+        }
+
+        match ext {
+            Some(ext) => format!("{name}.{ext}"),
+            None => name,
+        }
+    }
+
+    /// Draws naming settings editor.
+    pub fn input(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("name template:");
+            ui.text_edit_singleline(&mut self.template)
+                .on_hover_ui(|ui| {
+                    for (placeholder, description) in FileNaming::PLACEHOLDERS {
+                        ui.label(format!("{placeholder} - {description}"));
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("preset:");
+            ui.text_edit_singleline(&mut self.preset);
+        });
+    }
+}
+
+enum SinkTarget {
+    /// Each file is saved separately.
+    ///
+    /// - Native: file is written to the folder via fs
+    /// - WASM: file is downloaded via browser (folder is ignored)
+    Files(PathBuf),
+    /// Files are collected in memory and saved as a single zip archive by [ExportSink::finish].
+    Zip {
+        /// Archive location (on wasm side only file name is used).
+        archive: PathBuf,
+        writer: ZipWriter<Cursor<Vec<u8>>>,
+    },
+}
+
+/// Destination of the exported files.
+///
+/// Files are named according to [FileNaming]. Sink never overwrites files: if the name was
+/// already written during this export or (on native) file exists on disk, the file is refused
+/// and reported by [ExportSink::finish] (together with the files failed to be written).
+pub struct ExportSink {
+    target: SinkTarget,
+    naming: FileNaming,
+    written: BTreeSet<String>,
+    refused: Vec<String>,
+}
+
+impl ExportSink {
+    /// Creates sink saving files to `save_folder`.
+    ///
+    /// If `zip_name` is set all files are collected into `{save_folder}/{zip_name}-{timestamp}.zip`.
+    pub fn new(save_folder: PathBuf, zip_name: Option<&str>, naming: FileNaming) -> Self {
+        let target = match zip_name {
+            Some(zip_name) => {
+                let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                SinkTarget::Zip {
+                    archive: save_folder.join(format!("{zip_name}-{timestamp}.zip")),
+                    writer: ZipWriter::new(Cursor::new(vec![])),
+                }
+            }
+            None => SinkTarget::Files(save_folder),
+        };
+
+        Self {
+            target,
+            naming,
+            written: BTreeSet::new(),
+            refused: vec![],
+        }
+    }
+
+    /// Saves file named from `parts` (see [FileNaming::render]).
+    pub fn write(&mut self, parts: &NameParts, ext: Option<&str>, content: &[u8]) {
+        let filename = self.naming.render(parts, ext);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let exists = matches!(
+            &self.target,
+            SinkTarget::Files(save_folder) if save_folder.join(&filename).exists()
+        );
+        #[cfg(target_arch = "wasm32")]
+        let exists = false; // browser renames downloads itself
+        if exists || !self.written.insert(filename.clone()) {
+            tracing::warn!("{filename} already exists, refused to overwrite");
+            self.refused.push(filename);
+            return;
+        }
+
+        let result = match &mut self.target {
+            SinkTarget::Files(save_folder) => {
+                #[cfg(not(target_arch = "wasm32"))]
+                let result = std::fs::write(save_folder.join(&filename), content)
+                    .map_err(|err| err.to_string());
+                #[cfg(target_arch = "wasm32")]
+                let result = {
+                    let _ = save_folder;
+                    download_bytes(&filename, content);
+                    Ok(())
+                };
+                result
+            }
+            SinkTarget::Zip { writer, .. } => writer
+                .start_file(
+                    filename.as_str(),
+                    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+                )
+                .map_err(|err| err.to_string())
+                .and_then(|_| writer.write_all(content).map_err(|err| err.to_string())),
+        };
+
+        if let Err(err) = result {
+            tracing::error!("failed to write {filename}: {err}");
+            self.refused.push(format!("{filename}: {err}"));
+        }
+    }
+
+    /// Finalizes export (writes or downloads zip archive, nothing to do for separate files).
+    ///
+    /// Returns names of the files refused to be overwritten or failed to be written.
+    pub fn finish(mut self) -> Vec<String> {
+        if let SinkTarget::Zip { archive, writer } = self.target {
+            let data = match writer.finish() {
+                Ok(cursor) => cursor.into_inner(),
+                Err(err) => {
+                    tracing::error!("failed to finish {archive:?}: {err}");
+                    self.refused
+                        .push(format!("{}: {err}", archive.to_string_lossy()));
+                    return self.refused;
+                }
+            };
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                if archive.exists() {
+                    tracing::warn!("{archive:?} already exists, refused to overwrite");
+                    let archive = archive.to_string_lossy().to_string();
+                    return self.refused.into_iter().chain([archive]).collect();
+                }
+                if let Err(err) = std::fs::write(&archive, data) {
+                    tracing::error!("failed to write {archive:?}: {err}");
+                    self.refused
+                        .push(format!("{}: {err}", archive.to_string_lossy()));
+                }
+            }
+            #[cfg(target_arch = "wasm32")]
+            download_bytes(archive.file_name().unwrap().to_str().unwrap(), &data);
+        }
+
+        self.refused
+    }
+}
+
+/// Prefix of every provenance header line (commented line for most tsv readers).
+pub const HEADER_PREFIX: &str = "# ";
+
+/// Provenance information prepended to every exported table.
+///
+/// Header is a block of commented `# key: value` lines, where parameters are serialized to json.
+/// It can be parsed back with [ExportHeader::parse] to restore processing parameters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportHeader {
+    pub version: String,
+    /// Export time (RFC 3339, UTC).
+    pub exported: String,
+    pub process: ProcessParams,
+    pub post_process: PostProcessParams,
+    pub histogram: HistogramParams,
+    pub dead_time: DeadTimeParams,
+    #[serde(default)]
+    pub time_window: TimeWindow,
+    #[serde(default)]
+    pub event_filter: EventFilter,
+    /// Source point files with modification times (RFC 3339, UTC) if known.
+    pub sources: Vec<(String, Option<String>)>,
+}
+
+impl ExportHeader {
+    /// Creates header for current parameters.
+    ///
+    /// # Arguments
+    /// * `processing_params` - A ref copy of [ViewerState] to get processing parameters.
+    /// * `dead_time` - Dead-time correction applied to exported rates.
+    /// * `time_window` - Processed time sub-interval of the points.
+    /// * `event_filter` - Event filters applied before histogramming.
+    /// * `state` - A ref copy of [DataViewerApp::state](crate::app::DataViewerApp) converted to vec,
+    ///   opened files are listed as sources.
+    ///
+    pub fn new(
+        processing_params: &ViewerState,
+        dead_time: &DeadTimeParams,
+        time_window: &TimeWindow,
+        event_filter: &EventFilter,
+        state: &[(&String, &PointState)],
+    ) -> Self {
+        let sources = state
+            .iter()
+            .filter(|(_, cache)| cache.opened)
+            .map(|(name, cache)| {
+                let modified = cache.modified.map(|modified| {
+                    chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339()
+                });
+                ((*name).clone(), modified)
+            })
+            .collect();
+
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            exported: chrono::Utc::now().to_rfc3339(),
+            process: processing_params.process.clone(),
+            post_process: processing_params.post_process,
+            histogram: processing_params.histogram.clone(),
+            dead_time: dead_time.clone(),
+            time_window: *time_window,
+            event_filter: event_filter.clone(),
+            sources,
+        }
+    }
+
+    /// Copy of the header with only `source` left in sources list (for per point exports).
+    pub fn for_source(&self, source: &str) -> Self {
+        let mut header = self.clone();
+        header.sources.retain(|(name, _)| name == source);
+        header
+    }
+
+    /// Serializes header to a block of commented lines (ends with a newline).
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let mut push = |key: &str, value: &str| {
+            text.push_str(&format!("{HEADER_PREFIX}{key}: {value}\n"));
+        };
+
+        push("viewer_version", &self.version);
+        push("exported", &self.exported);
+        push("process", &serde_json::to_string(&self.process).unwrap());
+        push(
+            "post_process",
+            &serde_json::to_string(&self.post_process).unwrap(),
+        );
+        push("histogram", &serde_json::to_string(&self.histogram).unwrap());
+        push("dead_time", &serde_json::to_string(&self.dead_time).unwrap());
+        if let Some(summary) = self.time_window.summary() {
+            push("time_window_summary", &summary);
+        }
+        push(
+            "time_window",
+            &serde_json::to_string(&self.time_window).unwrap(),
+        );
+        if let Some(summary) = self.event_filter.summary() {
+            push("event_filter_summary", &summary);
+        }
+        push(
+            "event_filter",
+            &serde_json::to_string(&self.event_filter).unwrap(),
+        );
+        for (name, modified) in &self.sources {
+            push(
+                "source",
+                &format!("{name}\t{}", modified.as_deref().unwrap_or("unknown")),
+            );
+        }
+
+        text
+    }
+
+    /// Parses header from the beginning of exported file content.
+    ///
+    /// Returns `None` if the content has no header or processing parameters are missing.
+    pub fn parse(content: &str) -> Option<Self> {
+        let mut version = None;
+        let mut exported = None;
+        let mut process = None;
+        let mut post_process = None;
+        let mut histogram = None;
+        let mut dead_time = None;
+        let mut time_window = None;
+        let mut event_filter = None;
+        let mut sources = vec![];
+
+        for line in content.lines() {
+            let Some(line) = line.strip_prefix(HEADER_PREFIX) else {
+                break;
+            };
+            let Some((key, value)) = line.split_once(": ") else {
+                continue;
+            };
+
+            match key {
+                "viewer_version" => version = Some(value.to_owned()),
+                "exported" => exported = Some(value.to_owned()),
+                "process" => process = serde_json::from_str(value).ok(),
+                "post_process" => post_process = serde_json::from_str(value).ok(),
+                "histogram" => histogram = serde_json::from_str(value).ok(),
+                "dead_time" => dead_time = serde_json::from_str(value).ok(),
+                "time_window" => time_window = serde_json::from_str(value).ok(),
+                "event_filter" => event_filter = serde_json::from_str(value).ok(),
+                "source" => {
+                    let (name, modified) = value.split_once('\t').unwrap_or((value, "unknown"));
+                    let modified = (modified != "unknown").then(|| modified.to_owned());
+                    sources.push((name.to_owned(), modified));
+                }
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            version: version.unwrap_or_default(),
+            exported: exported.unwrap_or_default(),
+            process: process?,
+            post_process: post_process?,
+            histogram: histogram?,
+            dead_time: dead_time.unwrap_or_default(),
+            time_window: time_window.unwrap_or_default(),
+            event_filter: event_filter.unwrap_or_default(),
+            sources,
+        })
+    }
+
+    /// Processing parameters stored in the header.
+    ///
+    /// Returned state is marked as changed, so all opened points will be reprocessed on apply.
+    pub fn viewer_state(&self) -> ViewerState {
+        ViewerState {
+            process: self.process.clone(),
+            post_process: self.post_process,
+            histogram: self.histogram.clone(),
+            changed: true,
+        }
+    }
+}
+
+/// Single processed event.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EventRecord {
+    /// Time from the point start (in ns).
+    pub time: u64,
+    /// Zero-based channel id.
+    pub channel: u8,
+    pub amplitude: f32,
+    /// Event size (in bins).
+    pub size: u32,
+}
+
+/// Processed events of a point together with its metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointEvents {
+    pub filepath: String,
+    /// Point meta serialized to json.
+    pub meta: Option<String>,
+    pub hv: f32,
+    /// Point start time (RFC 3339, UTC).
+    pub start_time: String,
+    /// Acquisition time (in s).
+    pub acquisition_time: f64,
+    /// Effective time (in s) according to `cut_bad_blocks` post processing option
+    /// (reduced to the time window and the time-in-point window of the event filter).
+    pub effective_time: f64,
+    pub events: Vec<EventRecord>,
+}
+
+/// Layout of the MessagePack events file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventsFile {
+    pub header: ExportHeader,
+    pub point: PointEvents,
+}
+
+impl PointEvents {
+    /// Collects events of the processed point passed the `filter`.
+    ///
+    /// # Arguments
+    /// * `filepath` - point path (meta is loaded from it).
+    /// * `events` - point events processed with the `time_window` (see [process_point_events](crate::process_point_events)).
+    /// * `cut_bad_blocks` - post processing option used to calculate effective time.
+    ///
+    pub async fn collect(
+        filepath: PathBuf,
+        events: CachedEvents,
+        cut_bad_blocks: bool,
+        time_window: TimeWindow,
+        filter: &EventFilter,
+    ) -> Self {
+        let meta = load_meta(&filepath)
+            .await
+            .and_then(|meta| serde_json::to_string(&meta).ok());
+
+        let CachedEvents {
+            events, preprocess, ..
+        } = events;
+
+        let mut records = vec![];
+        for (time, timed_event) in filter.apply(events) {
+            for (offset, event) in timed_event {
+                if let FrameEvent::Event {
+                    channel,
+                    amplitude,
+                    size,
+                } = event
+                {
+                    records.push(EventRecord {
+                        time: time + offset as u64,
+                        channel,
+                        amplitude,
+                        size: size as u32,
+                    });
+                }
+            }
+        }
+
+        Self {
+            filepath: filepath.to_str().unwrap().to_owned(),
+            meta,
+            hv: preprocess.hv,
+            start_time: preprocess.start_time.and_utc().to_rfc3339(),
+            acquisition_time: preprocess.acquisition_time as f64 * 1e-9,
+            effective_time: filtered_time(&preprocess, cut_bad_blocks, &time_window, filter),
+            events: records,
+        }
+    }
+
+    /// Point metadata as commented header lines (see [HEADER_PREFIX]).
+    pub fn header_text(&self) -> String {
+        let mut text = String::new();
+        text.push_str(&format!("{HEADER_PREFIX}hv: {}\n", self.hv));
+        text.push_str(&format!("{HEADER_PREFIX}start_time: {}\n", self.start_time));
+        text.push_str(&format!(
+            "{HEADER_PREFIX}acquisition_time: {}\n",
+            self.acquisition_time
+        ));
+        text.push_str(&format!(
+            "{HEADER_PREFIX}effective_time: {}\n",
+            self.effective_time
+        ));
+        if let Some(meta) = &self.meta {
+            text.push_str(&format!("{HEADER_PREFIX}meta: {meta}\n"));
+        }
+        text
+    }
+
+    /// Events as tsv table (without header block).
+    pub fn to_tsv(&self) -> String {
+        let mut content = String::from("time\tchannel\tamplitude\tsize\n");
+        for EventRecord {
+            time,
+            channel,
+            amplitude,
+            size,
+        } in &self.events
+        {
+            content.push_str(&format!("{time}\t{channel}\t{amplitude}\t{size}\n"));
+        }
+        content
+    }
+}
+
+/// Point meta flattened to `key.subkey -> value` pairs.
+pub type FlatMeta = BTreeMap<String, String>;
+
+/// Flattens serialized meta into dot separated keys (arrays are kept as json).
+pub fn flatten_meta(meta: &NumassMeta) -> FlatMeta {
+    fn flatten(prefix: &str, value: &serde_json::Value, out: &mut FlatMeta) {
+        let key = |name: &str| {
+            if prefix.is_empty() {
+                name.to_owned()
+            } else {
+                format!("{prefix}.{name}")
+            }
+        };
+        match value {
+            serde_json::Value::Object(map) => {
+                for (name, value) in map {
+                    flatten(&key(name), value, out)
+                }
+            }
+            serde_json::Value::Null => {
+                out.insert(prefix.to_owned(), String::new());
+            }
+            serde_json::Value::String(value) => {
+                out.insert(prefix.to_owned(), value.clone());
+            }
+            value => {
+                out.insert(prefix.to_owned(), value.to_string());
+            }
+        }
+    }
+
+    let mut out = FlatMeta::new();
+    if let Ok(value) = serde_json::to_value(meta) {
+        flatten("", &value, &mut out);
+    }
+    out
+}
+
+/// Loads point meta and flattens it (see [flatten_meta]).
+pub async fn load_flat_meta(filepath: PathBuf) -> Option<FlatMeta> {
+    load_meta(&filepath).await.map(|meta| flatten_meta(&meta))
+}
+
+/// Optional columns of PPV/PPT tables.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExtraColumns {
+    /// Flattened meta keys (see [flatten_meta]) added as columns.
+    pub meta_keys: Vec<String>,
+    /// Add number of bad blocks (from [Preprocess](processing::preprocess::Preprocess)).
+    pub bad_blocks: bool,
+    /// Add number of channels with events (before event filters, see [ChannelCounts]).
+    pub channels: bool,
+}
+
+impl ExtraColumns {
+    /// Header part of the extra columns (each column is prefixed with a tab).
+    pub fn header(&self) -> String {
+        let mut header = String::new();
+        for key in &self.meta_keys {
+            header.push_str(&format!("\t{key}"));
+        }
+        if self.bad_blocks {
+            header.push_str("\tbad_blocks");
+        }
+        if self.channels {
+            header.push_str("\tchannels");
+        }
+        header
+    }
+
+    /// Values of the extra columns for the point (each value is prefixed with a tab).
+    ///
+    /// Missing values are left empty.
+    pub fn values(
+        &self,
+        meta: Option<&FlatMeta>,
+        cache: &PointState,
+        channel_counts: Option<&ChannelCounts>,
+    ) -> String {
+        let mut values = String::new();
+        for key in &self.meta_keys {
+            let value = meta.and_then(|meta| meta.get(key));
+            values.push_str(&format!("\t{}", value.map_or("", |value| value.as_str())));
+        }
+        if self.bad_blocks {
+            let bad_blocks = cache
+                .preprocess
+                .as_ref()
+                .map_or(String::new(), |preprocess| {
+                    preprocess.bad_blocks.len().to_string()
+                });
+            values.push_str(&format!("\t{bad_blocks}"));
+        }
+        if self.channels {
+            let channels = channel_counts.map_or(String::new(), |counts| counts.len().to_string());
+            values.push_str(&format!("\t{channels}"));
+        }
+        values
+    }
+
+    /// Draws columns selector.
+    ///
+    /// # Arguments
+    /// * `available_keys` - meta keys found in the scanned points.
+    ///
+    pub fn input(&mut self, ui: &mut Ui, available_keys: &BTreeSet<String>) {
+        ui.checkbox(&mut self.bad_blocks, "bad blocks");
+        ui.checkbox(&mut self.channels, "channels");
+
+        let keys = available_keys
+            .iter()
+            .chain(
+                self.meta_keys
+                    .iter()
+                    .filter(|key| !available_keys.contains(*key)),
+            )
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for key in keys {
+            let mut checked = self.meta_keys.contains(&key);
+            if ui.checkbox(&mut checked, &key).changed() {
+                if checked {
+                    self.meta_keys.push(key);
+                } else {
+                    self.meta_keys.retain(|selected| selected != &key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use crate::dead_time::DeadTimeModel;
+
+    use super::*;
+
+    fn point(opened: bool, modified: Option<SystemTime>) -> PointState {
+        PointState {
+            opened,
+            histogram: None,
+            preprocess: None,
+            modified,
+            counts: None,
+        }
+    }
+
+    fn json(value: &impl Serialize) -> String {
+        serde_json::to_string(value).unwrap()
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let params = ViewerState::default();
+        let dead_time = DeadTimeParams {
+            model: DeadTimeModel::Paralyzable,
+            default_tau: 320.0,
+            tau: BTreeMap::from([(2, 410.0)]),
+        };
+        let time_window = TimeWindow {
+            enabled: true,
+            start: 1.5,
+            end: 20.0,
+        };
+        let event_filter = EventFilter {
+            excluded_channels: BTreeSet::from([0, 6]),
+            time_enabled: true,
+            reset_window: 100,
+            ..Default::default()
+        };
+        let names = [
+            "/data/run/set_1/p1(30s)(HV1=14000)",
+            "/data/run/set_1/p2",
+            "/data/run/set_1/p3",
+        ]
+        .map(str::to_owned);
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let points = [
+            point(true, Some(modified)),
+            point(true, None),
+            point(false, Some(modified)),
+        ];
+        let state = names.iter().zip(points.iter()).collect::<Vec<_>>();
+
+        let header = ExportHeader::new(&params, &dead_time, &time_window, &event_filter, &state);
+        let content = format!("{}\"p1\"\t14000\t1.0\n", header.to_text());
+        let parsed = ExportHeader::parse(&content).unwrap();
+
+        assert_eq!(parsed.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(parsed.exported, header.exported);
+        assert_eq!(
+            parsed.sources,
+            vec![
+                (
+                    names[0].clone(),
+                    Some("2023-11-14T22:13:20+00:00".to_owned())
+                ),
+                (names[1].clone(), None),
+            ]
+        );
+        assert_eq!(parsed.dead_time, dead_time);
+        assert_eq!(parsed.time_window, time_window);
+        assert_eq!(parsed.event_filter, event_filter);
+
+        let restored = parsed.viewer_state();
+        assert_eq!(json(&restored.process), json(&params.process));
+        assert_eq!(json(&restored.post_process), json(&params.post_process));
+        assert_eq!(json(&restored.histogram), json(&params.histogram));
+        assert!(restored.changed);
+    }
+
+    #[test]
+    fn header_ends_at_first_data_line() {
+        let header = ExportHeader::new(
+            &ViewerState::default(),
+            &DeadTimeParams::default(),
+            &TimeWindow::default(),
+            &EventFilter::default(),
+            &[],
+        );
+        let content = format!(
+            "{}\"p1\"\t1\n{HEADER_PREFIX}source: p2\tunknown\n",
+            header.to_text()
+        );
+        assert!(ExportHeader::parse(&content).unwrap().sources.is_empty());
+
+        assert!(ExportHeader::parse("\"p1\"\t1\n").is_none());
+    }
+
+    fn parts(run: &str, set: &str, name: &str) -> NameParts {
+        NameParts {
+            name: name.to_owned(),
+            run: run.to_owned(),
+            set: set.to_owned(),
+            mode: "hist".to_owned(),
+            hv: None,
+            date: chrono::NaiveDate::from_ymd_opt(2024, 3, 5)
+                .and_then(|date| date.and_hms_opt(12, 0, 0)),
+        }
+    }
+
+    fn naming(template: &str) -> FileNaming {
+        FileNaming {
+            template: template.to_owned(),
+            preset: String::new(),
+        }
+    }
+
+    #[test]
+    fn render_all_placeholders() {
+        let naming = FileNaming {
+            template: "{run}-{set}-{name}_{hv}_{date} {mode}-{preset}".to_owned(),
+            preset: "fast".to_owned(),
+        };
+        let parts = NameParts {
+            hv: Some(14000.0),
+            ..parts("run_1", "set_2", "p3")
+        };
+        assert_eq!(
+            naming.render(&parts, Some("tsv")),
+            "run_1-set_2-p3_14000_20240305 hist-fast.tsv"
+        );
+    }
+
+    #[test]
+    fn render_drops_empty_placeholders() {
+        let default = naming("{run}-{set}-{name}");
+        assert_eq!(default.render(&parts("", "set", "p1"), None), "set-p1");
+        assert_eq!(default.render(&parts("run", "", "p1"), None), "run-p1");
+        assert_eq!(default.render(&parts("", "", "p1"), None), "p1");
+        assert_eq!(
+            default.render(&parts("run", "set", "p1"), Some("tsv")),
+            "run-set-p1.tsv"
+        );
+        // only one separator is dropped together with a placeholder
+        assert_eq!(
+            naming("{name}__{preset}").render(&parts("", "", "p1"), None),
+            "p1_"
+        );
+        // other literal parts are kept
+        assert_eq!(
+            naming("{name} - {mode}").render(&parts("", "", "p1"), None),
+            "p1 - hist"
+        );
+    }
+
+    #[test]
+    fn render_fallbacks_to_name() {
+        assert_eq!(
+            naming("{preset}{hv}").render(&parts("run", "set", "p1"), Some("txt")),
+            "p1.txt"
+        );
+    }
+
+    #[test]
+    fn render_replaces_path_separators() {
+        assert_eq!(
+            naming("{set}-{name}").render(&parts("", "../set", "a\\b:c"), None),
+            ".._set-a_b_c"
+        );
+    }
+
+    /// Empty temporary folder for the sink tests.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn temp_folder(name: &str) -> PathBuf {
+        let folder =
+            std::env::temp_dir().join(format!("data-viewer-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&folder);
+        std::fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn sink_refuses_overwrite() {
+        let folder = temp_folder("sink-files");
+        std::fs::write(folder.join("existing.tsv"), "old").unwrap();
+
+        let mut sink = ExportSink::new(folder.clone(), None, naming("{name}"));
+        sink.write(&parts("", "", "p1"), Some("tsv"), b"first");
+        sink.write(&parts("", "", "p1"), Some("tsv"), b"second");
+        sink.write(&parts("", "", "existing"), Some("tsv"), b"new");
+        let refused = sink.finish();
+
+        assert_eq!(refused, vec!["p1.tsv", "existing.tsv"]);
+        assert_eq!(
+            std::fs::read_to_string(folder.join("p1.tsv")).unwrap(),
+            "first"
+        );
+        assert_eq!(
+            std::fs::read_to_string(folder.join("existing.tsv")).unwrap(),
+            "old"
+        );
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn zip_sink_refuses_duplicates() {
+        let folder = temp_folder("sink-zip");
+
+        let mut sink = ExportSink::new(folder.clone(), Some("export"), naming("{name}"));
+        sink.write(&parts("", "", "p1"), Some("tsv"), b"first");
+        sink.write(&parts("", "", "p1"), Some("tsv"), b"second");
+        sink.write(&parts("", "", "p2"), Some("tsv"), b"other");
+        assert_eq!(sink.finish(), vec!["p1.tsv"]);
+
+        let archives = std::fs::read_dir(&folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(archives.len(), 1);
+        assert!(archives[0].starts_with("export-") && archives[0].ends_with(".zip"));
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+//! Include/exclude glob filter for the file tree widgets.
+use egui::{Color32, Ui};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/// Separator of the patterns in the include/exclude fields.
+const PATTERNS_SEPARATOR: char = ';';
+
+/// File tree filter.
+///
+/// File is shown if its full path matches any of the include patterns (or include list is empty)
+/// and matches none of the exclude patterns. Patterns without glob meta characters are treated
+/// as substrings (`abc` is the same as `*abc*`).
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    /// Include patterns separated by `;` (as typed by user).
+    pub include: String,
+    /// Exclude patterns separated by `;` (as typed by user).
+    pub exclude: String,
+
+    include_set: Option<GlobSet>,
+    exclude_set: Option<GlobSet>,
+    /// Compilation error of the last edit (previous valid filter is kept meanwhile).
+    error: Option<String>,
+}
+
+fn compile(patterns: &str) -> Result<Option<GlobSet>, globset::Error> {
+    let mut builder = GlobSetBuilder::new();
+    let mut empty = true;
+
+    for pattern in patterns
+        .split(PATTERNS_SEPARATOR)
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+    {
+        let pattern = if pattern.contains(['*', '?', '[', '{']) {
+            pattern.to_owned()
+        } else {
+            format!("*{pattern}*")
+        };
+        builder.add(Glob::new(&pattern)?);
+        empty = false;
+    }
+
+    if empty {
+        Ok(None)
+    } else {
+        builder.build().map(Some)
+    }
+}
+
+impl FileFilter {
+    /// Checks if file with `path` should be visible.
+    pub fn matches(&self, path: &str) -> bool {
+        self.include_set
+            .as_ref()
+            .is_none_or(|include| include.is_match(path))
+            && !self
+                .exclude_set
+                .as_ref()
+                .is_some_and(|exclude| exclude.is_match(path))
+    }
+
+    fn update(&mut self) {
+        match (compile(&self.include), compile(&self.exclude)) {
+            (Ok(include_set), Ok(exclude_set)) => {
+                self.include_set = include_set;
+                self.exclude_set = exclude_set;
+                self.error = None;
+            }
+            (Err(err), _) | (_, Err(err)) => self.error = Some(err.to_string()),
+        }
+    }
+
+    /// Draws filter editor.
+    ///
+    /// Returns true if user requested to mark all visible files ("+" button).
+    pub fn input(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        let mut needs_to_be_marked = false;
+
+        ui.horizontal(|ui| {
+            ui.label("include:");
+            changed |= ui
+                .add_sized([100.0, 20.0], egui::TextEdit::singleline(&mut self.include))
+                .on_hover_text("glob шаблоны через `;`, например `**/set_1*/p*`")
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("exclude:");
+            changed |= ui
+                .add_sized([100.0, 20.0], egui::TextEdit::singleline(&mut self.exclude))
+                .on_hover_text("glob шаблоны через `;`, например `**/*HV*`")
+                .changed();
+            needs_to_be_marked = ui
+                .button("+")
+                .on_hover_text("Выделить все видимые файлы")
+                .clicked();
+        });
+
+        if changed {
+            self.update();
+        }
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::RED, error);
+        }
+
+        needs_to_be_marked
+    }
+}
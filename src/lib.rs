@@ -1,20 +1,50 @@
 #![warn(clippy::all, rust_2018_idioms)]
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
 
 use app::ProcessingStatus;
 use egui::mutex::Mutex;
-use processing::{utils::events_to_histogram, viewer::EMPTY_POINT};
+use event_filter::EventFilter;
+use events_cache::CachedEvents;
+use processing::viewer::EMPTY_POINT;
+use time_window::TimeWindow;
 
 use processing::{
-    histogram::HistogramParams, postprocess::PostProcessParams, process::ProcessParams,
-    viewer::PointState,
+    histogram::HistogramParams, numass::NumassMeta, postprocess::PostProcessParams,
+    process::ProcessParams, viewer::PointState,
 };
 
+pub mod annotations;
 pub mod app;
 pub mod bundle_viewer;
+pub mod coincidence;
+pub mod dead_time;
+pub mod event_filter;
+pub mod events_cache;
+pub mod export;
+pub mod file_filter;
 pub mod filtered_viewer;
+pub mod memory_budget;
+pub mod meta_filter;
+pub mod point_status;
+pub mod point_table;
 pub mod point_viewer;
+pub mod processing_log;
+pub mod rates;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod root_jobs;
+pub mod roots;
+pub mod settings;
+pub mod time_window;
+pub mod tree_selection;
 pub mod trigger_viewer;
+#[cfg(target_arch = "wasm32")]
+pub mod worker_pool;
 
 /// Increment processed files counter and reset it if it is finished.
 pub fn inc_status(status: Arc<Mutex<ProcessingStatus>>) {
@@ -32,41 +62,123 @@ pub fn inc_status(status: Arc<Mutex<ProcessingStatus>>) {
 #[cfg(target_arch = "wasm32")]
 use gloo::worker::oneshot::oneshot;
 
+/// Web worker processing a point (see [worker_pool]).
+///
+/// Processed events are sent back only if requested by the last argument
+/// (they are needed for caching, coincidences and events export).
 #[cfg(target_arch = "wasm32")]
 #[oneshot]
 pub async fn PointProcessor(
-    args: (PathBuf, ProcessParams, PostProcessParams, HistogramParams),
-) -> Option<PointState> {
-    let (filepath, process, post_process, histogram) = args;
-    process_point(filepath, process, post_process, histogram).await
+    args: (u64, worker_pool::ProcessorArgs),
+) -> worker_pool::ProcessorOutput {
+    let (token, args) = args;
+    worker_pool::set_worker_token(token);
+    let (filepath, process, post_process, time_window, histogram, filter, keep_events) = args;
+    let events = process_point_events(filepath, process, post_process, time_window).await?;
+    Some(match events {
+        Some(events) if keep_events => {
+            let (state, channel_counts) = events.clone().into_point_state(histogram, &filter);
+            (state, channel_counts, Some(events))
+        }
+        Some(events) => {
+            let (state, channel_counts) = events.into_point_state(histogram, &filter);
+            (state, channel_counts, None)
+        }
+        None => (EMPTY_POINT, events_cache::ChannelCounts::new(), None),
+    })
 }
 
-pub async fn process_point(
+/// Loads point meta (from the numass-server in native `--server` mode, see [remote]).
+pub async fn load_meta(filepath: &Path) -> Option<NumassMeta> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(server) = remote::server() {
+        return server.load_meta(filepath).await;
+    }
+    processing::storage::load_meta(filepath).await
+}
+
+/// Loads point modification time (from the numass-server in native `--server` mode, see [remote]).
+pub async fn load_modified_time(filepath: PathBuf) -> Option<SystemTime> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(server) = remote::server() {
+        return server.modified_time(&filepath).await;
+    }
+    processing::storage::load_modified_time(filepath).await
+}
+
+/// Path of the point suitable for processing.
+///
+/// In native `--server` mode point is downloaded first (see [remote::RemoteServer::fetch_point]).
+pub async fn point_path(filepath: PathBuf) -> Option<PathBuf> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(server) = remote::server() {
+        return server.fetch_point(&filepath).await;
+    }
+    Some(filepath)
+}
+
+/// Same as [point_path] with already known modification time of the point (see [load_modified_time]).
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+pub async fn modified_point_path(
+    filepath: PathBuf,
+    modified: Option<SystemTime>,
+) -> Option<PathBuf> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(server) = remote::server() {
+        return server.fetch_point_modified(&filepath, modified).await;
+    }
+    Some(filepath)
+}
+
+/// Runs process/post-process pipeline for the point and drops events outside of the `time_window`.
+///
+/// Returns `None` if point can not be processed and `Some(None)` if it has no events.
+pub async fn process_point_events(
     filepath: PathBuf,
     process: ProcessParams,
     post_process: PostProcessParams,
-    histogram: HistogramParams,
-) -> Option<PointState> {
-    let modified = processing::storage::load_modified_time(filepath.clone()).await; // TODO: remove clone
+    time_window: TimeWindow,
+) -> Option<Option<CachedEvents>> {
+    let modified = load_modified_time(filepath.clone()).await;
+    let filepath = modified_point_path(filepath, modified).await?;
+    process_local_point_events(filepath, modified, process, post_process, time_window).await
+}
 
+/// Same as [process_point_events] for a point already available locally (see [point_path]).
+///
+/// `modified` is the modification time of the point itself (of the server version in native
+/// `--server` mode, see [load_modified_time]).
+pub async fn process_local_point_events(
+    filepath: PathBuf,
+    modified: Option<SystemTime>,
+    process: ProcessParams,
+    post_process: PostProcessParams,
+    time_window: TimeWindow,
+) -> Option<Option<CachedEvents>> {
     let events = processing::storage::process_point(&filepath, &process, Some(&post_process)).await;
 
     events.map(|(_, events)| {
-        if let Some((events, preprocess)) = events {
-
-            let histogram = events_to_histogram(events, histogram);
-
-            let counts = Some(histogram.events_all(None));
-
-            PointState {
-                opened: true,
-                histogram: Some(histogram),
-                preprocess: Some(preprocess),
-                modified,
-                counts,
-            }
-        } else {
-            EMPTY_POINT
-        }
+        events.map(|(events, preprocess)| CachedEvents {
+            events: time_window.apply(events),
+            preprocess,
+            modified,
+        })
     })
 }
+
+pub async fn process_point(
+    filepath: PathBuf,
+    process: ProcessParams,
+    post_process: PostProcessParams,
+    time_window: TimeWindow,
+    histogram: HistogramParams,
+    filter: EventFilter,
+) -> Option<PointState> {
+    process_point_events(filepath, process, post_process, time_window)
+        .await
+        .map(|events| {
+            events.map_or(EMPTY_POINT, |events| {
+                events.into_point_state(histogram, &filter).0
+            })
+        })
+}
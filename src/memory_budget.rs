@@ -0,0 +1,244 @@
+//! Memory budget of the processed points.
+//!
+//! Histograms of the unchecked points are evicted (least recently checked first) when total
+//! histograms size exceeds the budget. Evicted points keep their counts and preprocess info,
+//! histograms are rebuilt (from the [EventsCache] or by reprocessing) when the point is checked again.
+use std::{
+    collections::BTreeMap,
+    mem::{size_of, size_of_val},
+};
+
+use egui::Ui;
+use processing::{histogram::PointHistogram, viewer::PointState};
+
+use crate::events_cache::EventsCache;
+
+/// Default size of the histograms budget (in bytes).
+#[cfg(not(target_arch = "wasm32"))]
+pub const DEFAULT_HISTOGRAMS_BUDGET: usize = 512 * 1024 * 1024;
+/// Default size of the histograms budget (in bytes).
+#[cfg(target_arch = "wasm32")]
+pub const DEFAULT_HISTOGRAMS_BUDGET: usize = 128 * 1024 * 1024;
+
+const MB: usize = 1024 * 1024;
+
+/// Approximate memory usage of the `histogram` (in bytes).
+pub fn histogram_size(histogram: &PointHistogram) -> usize {
+    size_of::<PointHistogram>()
+        + histogram
+            .channels
+            .values()
+            .map(|bins| size_of_val(bins.as_slice()) + size_of::<(u8, Vec<f32>)>())
+            .sum::<usize>()
+}
+
+/// Checks if histogram of the processed point was evicted.
+///
+/// Points without events have zero counts and no histogram, they are not evicted.
+pub fn is_evicted(cache: &PointState) -> bool {
+    cache.histogram.is_none() && cache.counts.is_some_and(|counts| counts > 0)
+}
+
+/// Memory budget and LRU order of the points in [DataViewerApp::state](crate::app::DataViewerApp).
+#[derive(Debug)]
+pub struct MemoryBudget {
+    /// Histograms budget (in bytes).
+    pub budget: usize,
+    /// Tick of the last frame the point was checked.
+    last_used: BTreeMap<String, u64>,
+    tick: u64,
+    /// Histograms size after the last [MemoryBudget::enforce].
+    used: usize,
+    /// Number of histograms evicted so far.
+    evicted: usize,
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self {
+            budget: DEFAULT_HISTOGRAMS_BUDGET,
+            last_used: BTreeMap::new(),
+            tick: 0,
+            used: 0,
+            evicted: 0,
+        }
+    }
+}
+
+impl MemoryBudget {
+    /// Memory used by the histograms (in bytes).
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Updates usage of the points and evicts histograms of the unchecked points
+    /// until histograms fit into the budget. Checked points are never evicted.
+    pub fn enforce(&mut self, state: &mut BTreeMap<String, PointState>) {
+        self.tick += 1;
+        self.last_used.retain(|key, _| state.contains_key(key));
+
+        let mut used = 0;
+        let mut candidates = vec![];
+        for (key, cache) in state.iter() {
+            if cache.opened {
+                self.last_used.insert(key.clone(), self.tick);
+            }
+            if let Some(histogram) = &cache.histogram {
+                let size = histogram_size(histogram);
+                used += size;
+                if !cache.opened {
+                    let last_used = self.last_used.get(key).copied().unwrap_or_default();
+                    candidates.push((last_used, key.clone(), size));
+                }
+            }
+        }
+
+        if used > self.budget {
+            candidates.sort();
+            for (_, key, size) in candidates {
+                if used <= self.budget {
+                    break;
+                }
+                if let Some(cache) = state.get_mut(&key) {
+                    cache.histogram = None;
+                    used -= size;
+                    self.evicted += 1;
+                }
+            }
+        }
+
+        self.used = used;
+    }
+
+    /// Draws memory usage readout and budgets editor.
+    pub fn input(&mut self, ui: &mut Ui, events_cache: &mut EventsCache) {
+        egui::CollapsingHeader::new(format!(
+            "memory: {} MB",
+            (self.used + events_cache.used()) / MB
+        ))
+        .id_salt("memory_budget")
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let mut budget = self.budget / MB;
+                ui.label(format!("histograms: {} /", self.used / MB));
+                if ui
+                    .add(egui::DragValue::new(&mut budget).range(16..=16384).suffix(" MB"))
+                    .on_hover_text(
+                        "Бюджет гистограмм. При превышении гистограммы невыбранных точек выгружаются (давно не выбранные первыми)",
+                    )
+                    .changed()
+                {
+                    self.budget = budget * MB;
+                }
+            });
+            ui.horizontal(|ui| {
+                let mut budget = events_cache.budget / MB;
+                ui.label(format!(
+                    "events cache ({} points): {} /",
+                    events_cache.len(),
+                    events_cache.used() / MB
+                ));
+                if ui
+                    .add(egui::DragValue::new(&mut budget).range(0..=16384).suffix(" MB"))
+                    .on_hover_text(
+                        "Бюджет кэша событий (используется для перестроения гистограмм без повторной обработки)",
+                    )
+                    .changed()
+                {
+                    events_cache.budget = budget * MB;
+                    events_cache.shrink();
+                }
+            });
+            if self.evicted != 0 {
+                ui.label(format!("evicted histograms: {}", self.evicted));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(opened: bool) -> PointState {
+        let mut histogram = PointHistogram::new_step(0.0..100.0, 1.0);
+        histogram.add(0, 10.0);
+        PointState {
+            opened,
+            histogram: Some(histogram),
+            preprocess: None,
+            modified: None,
+            counts: Some(1),
+        }
+    }
+
+    fn size() -> usize {
+        histogram_size(point(false).histogram.as_ref().unwrap())
+    }
+
+    #[test]
+    fn evicts_least_recently_checked() {
+        let mut state = BTreeMap::from([
+            ("a".to_owned(), point(true)),
+            ("b".to_owned(), point(true)),
+            ("c".to_owned(), point(true)),
+        ]);
+        let mut memory = MemoryBudget::default();
+        memory.enforce(&mut state);
+        assert_eq!(memory.used(), 3 * size());
+
+        // "a" is unchecked before the others
+        state.get_mut("a").unwrap().opened = false;
+        memory.enforce(&mut state);
+        for cache in state.values_mut() {
+            cache.opened = false;
+        }
+        memory.budget = 2 * size();
+        memory.enforce(&mut state);
+
+        assert!(is_evicted(&state["a"]));
+        assert!(!is_evicted(&state["b"]));
+        assert!(!is_evicted(&state["c"]));
+        assert_eq!(memory.used(), 2 * size());
+        assert_eq!(memory.evicted, 1);
+    }
+
+    #[test]
+    fn checked_are_not_evicted() {
+        let mut state = BTreeMap::from([
+            ("a".to_owned(), point(true)),
+            ("b".to_owned(), point(false)),
+        ]);
+        let mut memory = MemoryBudget {
+            budget: 0,
+            ..Default::default()
+        };
+        memory.enforce(&mut state);
+
+        assert!(!is_evicted(&state["a"]));
+        assert!(is_evicted(&state["b"]));
+        assert_eq!(memory.used(), size());
+    }
+
+    #[test]
+    fn forgets_removed_points() {
+        let mut state = BTreeMap::from([("a".to_owned(), point(true))]);
+        let mut memory = MemoryBudget::default();
+        memory.enforce(&mut state);
+        state.clear();
+        memory.enforce(&mut state);
+        assert!(memory.last_used.is_empty());
+        assert_eq!(memory.used(), 0);
+    }
+
+    #[test]
+    fn empty_point_is_not_evicted() {
+        let mut cache = point(false);
+        cache.histogram = None;
+        assert!(is_evicted(&cache));
+        cache.counts = Some(0);
+        assert!(!is_evicted(&cache));
+        cache.counts = None;
+        assert!(!is_evicted(&cache));
+    }
+}
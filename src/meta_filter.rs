@@ -0,0 +1,243 @@
+//! Filtering of the file tree points by their meta (HV, start time, acquisition time, meta type).
+use chrono::{NaiveDate, NaiveDateTime};
+use egui::{Color32, Ui};
+use processing::numass::{ExternalMeta, NumassMeta, Reply};
+
+/// Meta values used by [MetaFilter].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PointMetaInfo {
+    pub hv: Option<f64>,
+    pub start_time: Option<NaiveDateTime>,
+    /// Acquisition time (in s).
+    pub acquisition_time: Option<f64>,
+    /// Meta variant name (like `Reply::AcquirePoint`).
+    pub meta_type: String,
+}
+
+impl PointMetaInfo {
+    pub fn from_meta(meta: &NumassMeta) -> Self {
+        let meta_type = match meta {
+            NumassMeta::Reply(Reply::AcquirePoint { .. }) => "Reply::AcquirePoint",
+            NumassMeta::Reply(_) => "Reply",
+            _ => "other",
+        }
+        .to_owned();
+
+        if let NumassMeta::Reply(Reply::AcquirePoint {
+            start_time,
+            acquisition_time,
+            external_meta,
+            ..
+        }) = meta
+        {
+            let hv = if let Some(ExternalMeta {
+                hv1_value: Some(hv),
+                ..
+            }) = external_meta
+            {
+                Some(*hv as f64)
+            } else {
+                None
+            };
+
+            PointMetaInfo {
+                hv,
+                start_time: Some(*start_time),
+                acquisition_time: Some(*acquisition_time as f64),
+                meta_type,
+            }
+        } else {
+            PointMetaInfo {
+                meta_type,
+                ..Default::default()
+            }
+        }
+    }
+}
+
+/// Parses time filter bound (`YYYY-MM-DD HH:MM[:SS]` or `YYYY-MM-DD`).
+fn parse_time(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// Points filter by meta values. Points without loaded meta are hidden while filter is enabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaFilter {
+    pub enabled: bool,
+
+    pub hv_enabled: bool,
+    pub hv_min: f64,
+    pub hv_max: f64,
+
+    /// Start time bounds as typed by user (empty - unbounded).
+    pub start_from: String,
+    pub start_to: String,
+
+    pub acquisition_enabled: bool,
+    pub acquisition_min: f64,
+    pub acquisition_max: f64,
+
+    /// Required meta type (any if None).
+    pub meta_type: Option<String>,
+}
+
+impl Default for MetaFilter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hv_enabled: false,
+            hv_min: 14000.0,
+            hv_max: 16000.0,
+            start_from: String::new(),
+            start_to: String::new(),
+            acquisition_enabled: false,
+            acquisition_min: 0.0,
+            acquisition_max: 1000.0,
+            meta_type: None,
+        }
+    }
+}
+
+impl MetaFilter {
+    /// Checks if point with meta `info` should be visible (`None` - meta is not loaded).
+    pub fn matches(&self, info: Option<&PointMetaInfo>) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let Some(info) = info else {
+            return false;
+        };
+
+        if self.hv_enabled
+            && !info
+                .hv
+                .is_some_and(|hv| (self.hv_min..=self.hv_max).contains(&hv))
+        {
+            return false;
+        }
+
+        if let Some(from) = parse_time(&self.start_from) {
+            if !info.start_time.is_some_and(|start_time| start_time >= from) {
+                return false;
+            }
+        }
+        if let Some(to) = parse_time(&self.start_to) {
+            if !info.start_time.is_some_and(|start_time| start_time <= to) {
+                return false;
+            }
+        }
+
+        if self.acquisition_enabled
+            && !info.acquisition_time.is_some_and(|acquisition_time| {
+                (self.acquisition_min..=self.acquisition_max).contains(&acquisition_time)
+            })
+        {
+            return false;
+        }
+
+        self.meta_type
+            .as_ref()
+            .is_none_or(|meta_type| meta_type == &info.meta_type)
+    }
+
+    /// Draws filter editor.
+    ///
+    /// # Arguments
+    /// * `meta_types` - meta types of the loaded points (choices for the type filter).
+    /// * `loading` - number of points with meta still loading.
+    ///
+    pub fn input(&mut self, ui: &mut Ui, meta_types: &[String], loading: usize) {
+        egui::CollapsingHeader::new("meta filter")
+            .id_salt("meta_filter")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.enabled, "enabled");
+                    if self.enabled && loading > 0 {
+                        ui.label(format!("loading meta: {loading}"));
+                        ui.spinner();
+                    }
+                });
+
+                ui.add_enabled_ui(self.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.hv_enabled, "HV");
+                        ui.add(
+                            egui::DragValue::new(&mut self.hv_min)
+                                .speed(10.0)
+                                .suffix(" V"),
+                        );
+                        ui.label("-");
+                        ui.add(
+                            egui::DragValue::new(&mut self.hv_max)
+                                .speed(10.0)
+                                .suffix(" V"),
+                        );
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("start from:");
+                        ui.add_sized(
+                            [120.0, 20.0],
+                            egui::TextEdit::singleline(&mut self.start_from),
+                        )
+                        .on_hover_text("YYYY-MM-DD [HH:MM[:SS]]");
+                        if ui.button("last week").clicked() {
+                            let from =
+                                chrono::Local::now().naive_local() - chrono::Duration::days(7);
+                            self.start_from = from.format("%Y-%m-%d %H:%M").to_string();
+                            self.start_to.clear();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("start to:");
+                        ui.add_sized(
+                            [120.0, 20.0],
+                            egui::TextEdit::singleline(&mut self.start_to),
+                        )
+                        .on_hover_text("YYYY-MM-DD [HH:MM[:SS]]");
+                    });
+                    for bound in [&self.start_from, &self.start_to] {
+                        if !bound.trim().is_empty() && parse_time(bound).is_none() {
+                            ui.colored_label(Color32::RED, format!("can't parse time {bound:?}"));
+                        }
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.acquisition_enabled, "acq. time");
+                        ui.add(
+                            egui::DragValue::new(&mut self.acquisition_min)
+                                .range(0.0..=f64::MAX)
+                                .suffix(" s"),
+                        );
+                        ui.label("-");
+                        ui.add(
+                            egui::DragValue::new(&mut self.acquisition_max)
+                                .range(0.0..=f64::MAX)
+                                .suffix(" s"),
+                        );
+                    });
+
+                    egui::ComboBox::from_label("meta type")
+                        .selected_text(self.meta_type.as_deref().unwrap_or("any"))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.meta_type, None, "any");
+                            for meta_type in meta_types {
+                                ui.selectable_value(
+                                    &mut self.meta_type,
+                                    Some(meta_type.clone()),
+                                    meta_type,
+                                );
+                            }
+                        });
+                });
+            });
+    }
+}
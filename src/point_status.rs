@@ -0,0 +1,116 @@
+//! Per-point processing records and staleness badges.
+//!
+//! [PointState] is shared with the processing crate, so parameters used for each point
+//! are kept in a separate [ProcessingRecords] map with the same keys.
+use std::{
+    collections::BTreeMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    time::SystemTime,
+};
+
+use egui::{Color32, Ui};
+use processing::viewer::{PointState, ViewerState};
+
+use crate::{
+    event_filter::EventFilter, events_cache::ChannelCounts, memory_budget::is_evicted,
+    time_window::TimeWindow,
+};
+
+/// Hashes of the processing parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParamsHash {
+    /// Hash of process, post process parameters and time window (changes require reprocessing).
+    pub process: u64,
+    /// Hash of histogram parameters and event filters (changes require only re-histogramming).
+    pub histogram: u64,
+}
+
+fn hash_json(value: &impl serde::Serialize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(value).unwrap().hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn params_hash(
+    params: &ViewerState,
+    time_window: &TimeWindow,
+    filter: &EventFilter,
+) -> ParamsHash {
+    ParamsHash {
+        process: hash_json(&(&params.process, &params.post_process, time_window)),
+        histogram: hash_json(&(&params.histogram, filter)),
+    }
+}
+
+/// Result of the last processing of a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessingRecord {
+    /// [params_hash] of the parameters point was processed with.
+    pub params_hash: ParamsHash,
+    /// Point could not be processed.
+    pub error: bool,
+}
+
+/// Processing records of the points.
+#[derive(Debug, Default)]
+pub struct ProcessingRecords {
+    pub records: BTreeMap<String, ProcessingRecord>,
+    /// Unfiltered per channel counts of the processed points (for dead-time corrections).
+    pub channel_counts: BTreeMap<String, ChannelCounts>,
+    /// Last known modification time of the files on disk.
+    pub disk_modified: BTreeMap<String, SystemTime>,
+    /// [params_hash] of the current parameters.
+    pub current_hash: ParamsHash,
+}
+
+impl ProcessingRecords {
+    pub fn badge(&self, key: &str, cache: &PointState) -> PointBadge {
+        let Some(record) = self.records.get(key) else {
+            return PointBadge::NotProcessed;
+        };
+        if record.error {
+            return PointBadge::Error;
+        }
+        if is_evicted(cache) {
+            return PointBadge::Evicted;
+        }
+        if let (Some(disk), Some(processed)) = (self.disk_modified.get(key), cache.modified) {
+            if *disk > processed {
+                return PointBadge::Modified;
+            }
+        }
+        if record.params_hash != self.current_hash {
+            PointBadge::Stale
+        } else {
+            PointBadge::UpToDate
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointBadge {
+    NotProcessed,
+    UpToDate,
+    /// Processed with other parameters.
+    Stale,
+    /// File was changed after processing.
+    Modified,
+    /// Histogram was evicted by the memory budget (see [MemoryBudget](crate::memory_budget::MemoryBudget)).
+    Evicted,
+    Error,
+}
+
+impl PointBadge {
+    /// Draws badge icon with hover description.
+    pub fn show(self, ui: &mut Ui) {
+        let (icon, color, description) = match self {
+            PointBadge::NotProcessed => ("○", Color32::GRAY, "не обработан"),
+            PointBadge::UpToDate => ("●", Color32::GREEN, "обработан с текущими параметрами"),
+            PointBadge::Stale => ("◐", Color32::YELLOW, "обработан с другими параметрами"),
+            PointBadge::Modified => ("◑", Color32::LIGHT_BLUE, "файл изменен после обработки"),
+            PointBadge::Evicted => ("◌", Color32::GRAY, "гистограмма выгружена из памяти"),
+            PointBadge::Error => ("✖", Color32::RED, "ошибка обработки"),
+        };
+        ui.colored_label(color, icon).on_hover_text(description);
+    }
+}
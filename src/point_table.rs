@@ -0,0 +1,286 @@
+//! Sortable table of the points (alternative to the file tree view).
+use std::{cmp::Ordering, collections::BTreeSet};
+
+use chrono::NaiveDateTime;
+use egui::Ui;
+use egui_extras::{Column, TableBuilder};
+
+/// Processing status of a point shown in the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PointStatus {
+    /// Point is not opened.
+    Closed,
+    /// Point is opened but has no results yet.
+    Pending,
+    /// Point is opened and processed.
+    Processed,
+}
+
+impl PointStatus {
+    fn label(self) -> &'static str {
+        match self {
+            PointStatus::Closed => "-",
+            PointStatus::Pending => "pending",
+            PointStatus::Processed => "processed",
+        }
+    }
+}
+
+/// Single row of the [PointTable].
+#[derive(Debug, Clone)]
+pub struct PointRow {
+    /// Point path (key of [DataViewerApp::state](crate::app::DataViewerApp)).
+    pub key: String,
+    pub set: String,
+    pub name: String,
+    pub hv: Option<f64>,
+    pub start_time: Option<NaiveDateTime>,
+    /// Acquisition time (in s).
+    pub acquisition_time: Option<f64>,
+    pub counts: Option<usize>,
+    /// Rate (in Hz, with dead time correction).
+    pub rate: Option<f64>,
+    pub status: PointStatus,
+    pub opened: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Set,
+    Name,
+    Hv,
+    StartTime,
+    AcquisitionTime,
+    Counts,
+    Rate,
+    Status,
+}
+
+const COLUMNS: [(SortColumn, &str); 8] = [
+    (SortColumn::Set, "set"),
+    (SortColumn::Name, "name"),
+    (SortColumn::Hv, "HV"),
+    (SortColumn::StartTime, "start time"),
+    (SortColumn::AcquisitionTime, "acq. time"),
+    (SortColumn::Counts, "counts"),
+    (SortColumn::Rate, "rate"),
+    (SortColumn::Status, "status"),
+];
+
+/// Compares optional values placing missing ones at the end.
+fn cmp_option<T: PartialOrd>(a: &Option<T>, b: &Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Table view state (sorting and selection).
+#[derive(Debug, Clone)]
+pub struct PointTable {
+    pub sort_by: SortColumn,
+    pub ascending: bool,
+    /// Selected rows keys.
+    pub selected: BTreeSet<String>,
+    /// Last clicked row (start of the shift-click range).
+    anchor: Option<String>,
+}
+
+impl Default for PointTable {
+    fn default() -> Self {
+        Self {
+            sort_by: SortColumn::Name,
+            ascending: true,
+            selected: BTreeSet::new(),
+            anchor: None,
+        }
+    }
+}
+
+impl PointTable {
+    fn sort(&self, rows: &mut [PointRow]) {
+        rows.sort_by(|a, b| {
+            let ordering = match self.sort_by {
+                SortColumn::Set => natord::compare(&a.set, &b.set),
+                SortColumn::Name => {
+                    natord::compare(&a.set, &b.set).then_with(|| natord::compare(&a.name, &b.name))
+                }
+                SortColumn::Hv => cmp_option(&a.hv, &b.hv),
+                SortColumn::StartTime => cmp_option(&a.start_time, &b.start_time),
+                SortColumn::AcquisitionTime => cmp_option(&a.acquisition_time, &b.acquisition_time),
+                SortColumn::Counts => cmp_option(&a.counts, &b.counts),
+                SortColumn::Rate => cmp_option(&a.rate, &b.rate),
+                SortColumn::Status => a.status.cmp(&b.status),
+            };
+            if self.ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+    }
+
+    /// Handles click on the row `idx` of the sorted `rows` (with shift/ctrl modifiers).
+    fn click(&mut self, rows: &[PointRow], idx: usize, modifiers: egui::Modifiers) {
+        let key = &rows[idx].key;
+
+        if modifiers.shift {
+            let anchor = self
+                .anchor
+                .as_ref()
+                .and_then(|anchor| rows.iter().position(|row| &row.key == anchor))
+                .unwrap_or(idx);
+            if !modifiers.command {
+                self.selected.clear();
+            }
+            self.selected.extend(
+                rows[anchor.min(idx)..=anchor.max(idx)]
+                    .iter()
+                    .map(|row| row.key.clone()),
+            );
+            return;
+        }
+
+        if modifiers.command {
+            if !self.selected.remove(key) {
+                self.selected.insert(key.clone());
+            }
+        } else {
+            self.selected.clear();
+            self.selected.insert(key.clone());
+        }
+        self.anchor = Some(key.clone());
+    }
+
+    /// Forgets selection of the rows which are not shown anymore (filtered out or closed).
+    fn retain_rows(&mut self, rows: &[PointRow]) {
+        let keys = rows.iter().map(|row| &row.key).collect::<BTreeSet<_>>();
+        self.selected.retain(|key| keys.contains(key));
+    }
+
+    /// Draws the table.
+    ///
+    /// Returns requested changes of the points `opened` flag. Toggling checkbox of a selected row
+    /// is applied to all selected (and shown) rows.
+    pub fn show(&mut self, ui: &mut Ui, mut rows: Vec<PointRow>) -> Vec<(String, bool)> {
+        self.retain_rows(&rows);
+        self.sort(&mut rows);
+
+        let mut changes = vec![];
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{} points, {} selected",
+                rows.len(),
+                self.selected.len()
+            ));
+            if ui.button("check selected").clicked() {
+                changes.extend(self.selected.iter().map(|key| (key.clone(), true)));
+            }
+            if ui.button("uncheck selected").clicked() {
+                changes.extend(self.selected.iter().map(|key| (key.clone(), false)));
+            }
+        });
+
+        let modifiers = ui.input(|i| i.modifiers);
+        let mut clicked = None;
+
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            let mut table = TableBuilder::new(ui)
+                .striped(true)
+                .sense(egui::Sense::click())
+                .column(Column::auto());
+            for _ in COLUMNS {
+                table = table.column(Column::auto().resizable(true));
+            }
+
+            table
+                .header(20.0, |mut header| {
+                    header.col(|_| {});
+                    for (column, title) in COLUMNS {
+                        header.col(|ui| {
+                            let title = if self.sort_by == column {
+                                format!("{title} {}", if self.ascending { "⏶" } else { "⏷" })
+                            } else {
+                                title.to_owned()
+                            };
+                            if ui.button(title).clicked() {
+                                if self.sort_by == column {
+                                    self.ascending = !self.ascending;
+                                } else {
+                                    self.sort_by = column;
+                                    self.ascending = true;
+                                }
+                            }
+                        });
+                    }
+                })
+                .body(|body| {
+                    body.rows(18.0, rows.len(), |mut row| {
+                        let idx = row.index();
+                        let point = &rows[idx];
+                        row.set_selected(self.selected.contains(&point.key));
+
+                        row.col(|ui| {
+                            let mut opened = point.opened;
+                            if ui.checkbox(&mut opened, "").changed() {
+                                if self.selected.contains(&point.key) {
+                                    changes.extend(
+                                        self.selected.iter().map(|key| (key.clone(), opened)),
+                                    );
+                                } else {
+                                    changes.push((point.key.clone(), opened));
+                                }
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label(&point.set);
+                        });
+                        row.col(|ui| {
+                            ui.label(&point.name);
+                        });
+                        row.col(|ui| {
+                            if let Some(hv) = point.hv {
+                                ui.label(format!("{hv:.1}"));
+                            }
+                        });
+                        row.col(|ui| {
+                            if let Some(start_time) = point.start_time {
+                                ui.label(start_time.format("%Y-%m-%d %H:%M:%S").to_string());
+                            }
+                        });
+                        row.col(|ui| {
+                            if let Some(acquisition_time) = point.acquisition_time {
+                                ui.label(format!("{acquisition_time:.1} s"));
+                            }
+                        });
+                        row.col(|ui| {
+                            if let Some(counts) = point.counts {
+                                ui.label(counts.to_string());
+                            }
+                        });
+                        row.col(|ui| {
+                            if let Some(rate) = point.rate {
+                                ui.label(format!("{rate:.2}"));
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label(point.status.label());
+                        });
+
+                        if row.response().clicked() {
+                            clicked = Some(idx);
+                        }
+                    });
+                });
+        });
+
+        if let Some(idx) = clicked {
+            self.click(&rows, idx, modifiers);
+        }
+
+        changes
+    }
+}
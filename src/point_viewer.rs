@@ -1,6 +1,6 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::BTreeSet, path::PathBuf, sync::Arc};
 
-use egui::{mutex::Mutex, Visuals};
+use egui::{mutex::Mutex, RichText, Visuals};
 use egui_plot::{GridMark, Legend};
 use processing::{
     numass::protos::rsb_event,
@@ -8,6 +8,9 @@ use processing::{
     types::{ProcessedWaveform, RawWaveform},
     utils::{color_for_index, correct_frame_time, EguiLine},
 };
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{load_setting, save_setting};
 
 #[cfg(not(target_arch = "wasm32"))]
 use tokio::spawn;
@@ -15,6 +18,9 @@ use tokio::spawn;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_futures::spawn_local as spawn;
 
+/// Name of the channel selection setting (see [crate::settings]).
+const SETTING: &str = "point-viewer";
+
 #[derive(Debug, Clone)]
 enum AppState {
     Initializing,
@@ -22,60 +28,497 @@ enum AppState {
     Interactive,
 }
 
-type Chunk = Vec<(u8, i64, ProcessedWaveform)>;
+/// Processed frame: channel id, time from the point start (in ns) and waveform.
+type Frame = (u8, u64, ProcessedWaveform);
+
+/// Default size of the time window (in ns).
+const DEFAULT_WINDOW_NS: u64 = 1_000_000;
+
+/// Number of bins of the frames density overview.
+const OVERVIEW_BINS: usize = 500;
+/// Height of the frames density overview (in points).
+const OVERVIEW_HEIGHT: f32 = 24.0;
+
+/// Window size presets (in ns).
+const WINDOW_PRESETS: [u64; 7] = [
+    1_000,
+    10_000,
+    100_000,
+    1_000_000,
+    10_000_000,
+    100_000_000,
+    1_000_000_000,
+];
+
+/// Shown channels of the [PointViewer].
+///
+/// Stored in `~/.config/numass-viewers/point-viewer.json` (native) or in the browser local storage (web),
+/// so selection is kept between points.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelSelection {
+    /// Hidden channels (zero-based ids).
+    pub hidden: BTreeSet<u8>,
+    /// The only shown channel (overrides [ChannelSelection::hidden]).
+    pub solo: Option<u8>,
+}
+
+impl ChannelSelection {
+    /// Loads stored selection (all channels shown if not stored yet or broken).
+    pub fn load() -> Self {
+        load_setting(SETTING)
+    }
+
+    fn save(&self) {
+        save_setting(SETTING, self);
+    }
+
+    pub fn is_visible(&self, channel: u8) -> bool {
+        match self.solo {
+            Some(solo) => solo == channel,
+            None => !self.hidden.contains(&channel),
+        }
+    }
+
+    /// Draws channel color legend with visibility toggles and solo selector.
+    ///
+    /// Selection is saved on change.
+    fn input(&mut self, ui: &mut egui::Ui, channels: &[u8]) {
+        let before = self.clone();
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("channels:");
+            for &channel in channels {
+                let visible = self.is_visible(channel);
+                let color = if visible {
+                    color_for_index(channel as usize)
+                } else {
+                    egui::Color32::DARK_GRAY
+                };
+                let response = ui
+                    .selectable_label(
+                        visible,
+                        RichText::new(format!("■ ch #{}", channel + 1)).color(color),
+                    )
+                    .on_hover_text("Показать/скрыть канал (двойной клик - только этот канал, клик в режиме solo - показать все)");
+                if response.double_clicked() {
+                    // revert the toggle made by the first click
+                    self.hidden.remove(&channel);
+                    self.solo = Some(channel);
+                } else if response.clicked() {
+                    if self.solo.is_some() {
+                        self.solo = None;
+                    } else if !self.hidden.remove(&channel) {
+                        self.hidden.insert(channel);
+                    }
+                }
+            }
+
+            ui.separator();
+            egui::ComboBox::from_id_salt("solo_channel")
+                .selected_text(match self.solo {
+                    Some(channel) => format!("solo: ch #{}", channel + 1),
+                    None => "solo: off".to_owned(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.solo, None, "off");
+                    for &channel in channels {
+                        ui.selectable_value(
+                            &mut self.solo,
+                            Some(channel),
+                            format!("ch #{}", channel + 1),
+                        );
+                    }
+                });
+        });
+
+        if *self != before {
+            self.save();
+        }
+    }
+}
+
+/// Frames density of the visible channels from [PointViewer::start_ns] to the last frame.
+struct Density {
+    /// Selection the density was computed for.
+    selection: ChannelSelection,
+    /// Start the density was computed for (in ns).
+    start_ns: u64,
+    /// Time span of all bins (in ns).
+    span_ns: u64,
+    bins: Vec<usize>,
+}
 
 pub struct PointViewer {
-    chunks: Arc<Mutex<Option<Vec<Chunk>>>>,
+    /// Frames of the point sorted by time.
+    frames: Arc<Mutex<Option<Vec<Frame>>>>,
+    /// Channels present in the point (filled on the first load).
+    channels: Vec<u8>,
+    selection: ChannelSelection,
     current_chunk: usize,
+    /// Size of the shown time window (in ns).
+    window_ns: u64,
+    /// Start of the first window from the point start (in ns).
+    start_ns: u64,
+    /// Cached density for the overview strip (see [PointViewer::update_density]).
+    density: Option<Density>,
+    /// Content of the "jump to time" input.
+    jump_time: String,
+    /// Frame number (one-based, in time order) of the "jump to frame" input.
+    jump_frame: usize,
     state: Arc<Mutex<AppState>>,
 }
 
-fn point_to_chunks(point: rsb_event::Point, limit_ns: u64) -> Vec<Chunk> {
-    let mut chunks = vec![];
-    chunks.push(vec![]);
+fn point_to_frames(point: rsb_event::Point) -> Vec<Frame> {
+    let mut frames = vec![];
 
     for channel in point.channels {
         for block in channel.blocks {
             for frame in block.frames {
-                let chunk_num = (correct_frame_time(frame.time) / limit_ns) as usize;
-
-                while chunks.len() < chunk_num + 1 {
-                    chunks.push(vec![])
-                }
-
                 let waveform = ProcessedWaveform::from(&RawWaveform::from(&frame));
-
-                chunks[chunk_num].push((
-                    channel.id as u8,
-                    (correct_frame_time(frame.time) % limit_ns) as i64,
-                    waveform,
-                ));
+                frames.push((channel.id as u8, correct_frame_time(frame.time), waveform));
             }
         }
     }
 
-    chunks
+    frames.sort_by_key(|(_, time, _)| *time);
+    frames
+}
+
+/// Formats time (in ns) in units suitable for its magnitude.
+fn format_time(time_ns: f64) -> String {
+    if time_ns < 1e3 {
+        format!("{time_ns:.0} ns")
+    } else if time_ns < 1e6 {
+        format!("{:.3} μs", time_ns * 1e-3)
+    } else if time_ns < 1e9 {
+        format!("{:.3} ms", time_ns * 1e-6)
+    } else {
+        format!("{:.3} s", time_ns * 1e-9)
+    }
+}
+
+/// Parses time with optional unit suffix (`ns`, `us`/`μs`, `ms`, `s`; ms by default) to ns.
+fn parse_time(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == 'e'))
+        .unwrap_or(text.len());
+    let (value, unit) = text.split_at(split);
+    let value = value.parse::<f64>().ok()?;
+    let scale = match unit.trim() {
+        "ns" => 1.0,
+        "us" | "μs" | "mks" => 1e3,
+        "" | "ms" => 1e6,
+        "s" => 1e9,
+        _ => return None,
+    };
+    Some(value * scale)
 }
 
 impl PointViewer {
     pub fn init_with_point(filepath: PathBuf) -> Self {
         let viewer = PointViewer {
-            chunks: Arc::new(Mutex::new(None)),
+            frames: Arc::new(Mutex::new(None)),
+            channels: vec![],
+            selection: ChannelSelection::load(),
             current_chunk: 0,
+            window_ns: DEFAULT_WINDOW_NS,
+            start_ns: 0,
+            density: None,
+            jump_time: String::new(),
+            jump_frame: 1,
             state: Arc::new(Mutex::new(AppState::Initializing)),
         };
 
-        let chunks = Arc::clone(&viewer.chunks);
+        let frames = Arc::clone(&viewer.frames);
         let state = Arc::clone(&viewer.state);
 
         spawn(async move {
             let point = load_point(&filepath).await;
-            *chunks.lock() = Some(point_to_chunks(point, 1_000_000));
+            *frames.lock() = Some(point_to_frames(point));
             *state.lock() = AppState::FirstLoad;
         });
 
         viewer
     }
+
+    /// Start of the `chunk`-th window from the point start (in ns).
+    fn chunk_start(&self, chunk: usize) -> u64 {
+        self.start_ns
+            .saturating_add(self.window_ns.saturating_mul(chunk as u64))
+    }
+
+    /// Number of windows from [PointViewer::start_ns] to the last frame (at least one).
+    fn chunks_count(&self, frames: &[Frame]) -> usize {
+        match frames.last() {
+            Some((_, last, _)) if *last >= self.start_ns => {
+                ((last - self.start_ns) / self.window_ns) as usize + 1
+            }
+            _ => 1,
+        }
+    }
+
+    /// Frames of the `chunk`-th window.
+    fn chunk<'a>(&self, frames: &'a [Frame], chunk: usize) -> &'a [Frame] {
+        let from = self.chunk_start(chunk);
+        let to = from.saturating_add(self.window_ns);
+        let lo = frames.partition_point(|(_, time, _)| *time < from);
+        let hi = frames.partition_point(|(_, time, _)| *time < to);
+        &frames[lo..hi]
+    }
+
+    /// Index of the nearest window after (`forward`) or before the current one with frames
+    /// on the visible channels.
+    fn next_visible_chunk(&self, frames: &[Frame], forward: bool) -> Option<usize> {
+        let visible = |(channel, time, _): &&Frame| {
+            *time >= self.start_ns && self.selection.is_visible(*channel)
+        };
+        let found = if forward {
+            let from = self.chunk_start(self.current_chunk + 1);
+            let lo = frames.partition_point(|(_, time, _)| *time < from);
+            frames[lo..].iter().find(visible)
+        } else {
+            let to = self.chunk_start(self.current_chunk);
+            let hi = frames.partition_point(|(_, time, _)| *time < to);
+            frames[..hi].iter().rev().find(visible)
+        };
+        found.map(|(_, time, _)| ((time - self.start_ns) / self.window_ns) as usize)
+    }
+
+    /// Moves to the next (`forward`) or previous window with visible frames.
+    fn step(&mut self, frames: &[Frame], forward: bool) {
+        if let Some(chunk) = self.next_visible_chunk(frames, forward) {
+            self.current_chunk = chunk;
+        }
+    }
+
+    /// Moves to the window containing `time` (in ns from the point start).
+    ///
+    /// Start of the first window is reset if `time` is before it.
+    fn jump_to_time(&mut self, time: u64) {
+        if time < self.start_ns {
+            self.start_ns = 0;
+        }
+        self.current_chunk = ((time - self.start_ns) / self.window_ns) as usize;
+    }
+
+    /// Draws non-empty window navigation and time/frame jump controls.
+    fn jump_controls(&mut self, ui: &mut egui::Ui, frames: &[Frame]) {
+        ui.horizontal(|ui| {
+            if ui
+                .button("⏪")
+                .on_hover_text("Предыдущее окно с кадрами на видимых каналах")
+                .clicked()
+            {
+                self.step(frames, false);
+            }
+            if ui
+                .button("⏩")
+                .on_hover_text("Следующее окно с кадрами на видимых каналах")
+                .clicked()
+            {
+                self.step(frames, true);
+            }
+
+            ui.separator();
+            ui.label("time:");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.jump_time)
+                    .desired_width(100.0)
+                    .hint_text("12.5 ms"),
+            );
+            let submitted =
+                response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui
+                .button("go")
+                .on_hover_text("Перейти к окну с заданным временем от начала точки (ns, us, ms, s; по умолчанию ms)")
+                .clicked()
+                || submitted
+            {
+                match parse_time(&self.jump_time) {
+                    Some(time) => self.jump_to_time(time.max(0.0) as u64),
+                    None => tracing::warn!("can't parse time {:?}", self.jump_time),
+                }
+            }
+
+            ui.separator();
+            ui.label("frame:");
+            ui.add(egui::DragValue::new(&mut self.jump_frame).range(1..=frames.len().max(1)));
+            ui.label(format!("/ {}", frames.len()));
+            if ui
+                .button("go")
+                .on_hover_text("Перейти к окну с кадром с заданным номером (по времени, все каналы)")
+                .clicked()
+            {
+                if let Some((_, time, _)) = frames.get(self.jump_frame - 1) {
+                    self.jump_to_time(*time);
+                }
+            }
+        });
+    }
+
+    /// Recomputes [PointViewer::density] if channel selection or start were changed.
+    fn update_density(&mut self, frames: &[Frame]) {
+        let outdated = match &self.density {
+            Some(density) => {
+                density.selection != self.selection || density.start_ns != self.start_ns
+            }
+            None => true,
+        };
+        if !outdated {
+            return;
+        }
+
+        let span_ns = frames
+            .last()
+            .map_or(0, |(_, last, _)| last.saturating_sub(self.start_ns))
+            + 1;
+        let mut bins = vec![0; OVERVIEW_BINS];
+        let lo = frames.partition_point(|(_, time, _)| *time < self.start_ns);
+        for (channel, time, _) in &frames[lo..] {
+            if self.selection.is_visible(*channel) {
+                let idx = ((time - self.start_ns) as f64 / span_ns as f64 * OVERVIEW_BINS as f64)
+                    as usize;
+                bins[idx.min(OVERVIEW_BINS - 1)] += 1;
+            }
+        }
+
+        self.density = Some(Density {
+            selection: self.selection.clone(),
+            start_ns: self.start_ns,
+            span_ns,
+            bins,
+        });
+    }
+
+    /// Draws frames density strip with the current window marker.
+    ///
+    /// Click or drag on the strip moves to the pointed time.
+    fn overview(&mut self, ui: &mut egui::Ui, frames: &[Frame]) {
+        self.update_density(frames);
+
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), OVERVIEW_HEIGHT),
+            egui::Sense::click_and_drag(),
+        );
+        let Some(density) = &self.density else {
+            return;
+        };
+        let span_ns = density.span_ns;
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        let max = density
+            .bins
+            .iter()
+            .max()
+            .copied()
+            .unwrap_or_default()
+            .max(1);
+        let bin_width = rect.width() / density.bins.len() as f32;
+        for (idx, count) in density.bins.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            // log scale so single frames are still visible next to bursts
+            let height = rect.height() * (*count as f32).ln_1p() / (max as f32).ln_1p();
+            let left = rect.left() + idx as f32 * bin_width;
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(left, rect.bottom() - height),
+                    egui::pos2(left + bin_width, rect.bottom()),
+                ),
+                0.0,
+                egui::Color32::from_rgb(80, 160, 255),
+            );
+        }
+
+        let to_x = |time: u64| {
+            rect.left()
+                + (time.saturating_sub(self.start_ns) as f64 / span_ns as f64) as f32 * rect.width()
+        };
+        let from = self.chunk_start(self.current_chunk);
+        let left = to_x(from);
+        painter.rect_stroke(
+            egui::Rect::from_min_max(
+                egui::pos2(left, rect.top()),
+                egui::pos2(
+                    to_x(from.saturating_add(self.window_ns)).max(left + 2.0),
+                    rect.bottom(),
+                ),
+            ),
+            0.0,
+            egui::Stroke::new(1.0, egui::Color32::YELLOW),
+            egui::StrokeKind::Inside,
+        );
+
+        if let Some(pos) = response.interact_pointer_pos() {
+            let fraction = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0) as f64;
+            self.jump_to_time(self.start_ns + (fraction * span_ns as f64) as u64);
+        }
+        response.on_hover_text("Плотность кадров на видимых каналах (клик - перейти к времени)");
+    }
+
+    /// Draws window size and start editors (start is limited by the last of the `frames`).
+    ///
+    /// Window size change keeps shown time, start change moves to the first window.
+    fn window_controls(&mut self, ui: &mut egui::Ui, frames: &[Frame]) {
+        let last_ms = frames
+            .last()
+            .map_or(0.0, |(_, time, _)| *time as f64 * 1e-6);
+        ui.horizontal(|ui| {
+            let shown = self.chunk_start(self.current_chunk);
+
+            ui.label("window:");
+            let mut window_us = self.window_ns as f64 * 1e-3;
+            let mut window_changed = ui
+                .add(
+                    egui::DragValue::new(&mut window_us)
+                        .range(1.0..=10_000_000.0)
+                        .speed(window_us * 0.01)
+                        .suffix(" μs"),
+                )
+                .on_hover_text("Размер окна (от 1 мкс до 10 с)")
+                .changed();
+            egui::ComboBox::from_id_salt("window_presets")
+                .selected_text(format_time(self.window_ns as f64))
+                .show_ui(ui, |ui| {
+                    for preset in WINDOW_PRESETS {
+                        if ui
+                            .selectable_label(self.window_ns == preset, format_time(preset as f64))
+                            .clicked()
+                        {
+                            window_us = preset as f64 * 1e-3;
+                            window_changed = true;
+                        }
+                    }
+                });
+
+            ui.label("start:");
+            let mut start_ms = self.start_ns as f64 * 1e-6;
+            let start_changed = ui
+                .add(
+                    egui::DragValue::new(&mut start_ms)
+                        .range(0.0..=last_ms)
+                        .speed(0.01)
+                        .suffix(" ms"),
+                )
+                .on_hover_text("Начало первого окна от начала точки")
+                .changed();
+
+            if window_changed {
+                self.window_ns = ((window_us * 1e3).round() as u64).max(1_000);
+                self.current_chunk =
+                    (shown.saturating_sub(self.start_ns) / self.window_ns) as usize;
+            }
+            if start_changed {
+                self.start_ns = (start_ms * 1e6).round() as u64;
+                self.current_chunk = 0;
+            }
+        });
+    }
 }
 
 impl eframe::App for PointViewer {
@@ -92,20 +535,40 @@ impl eframe::App for PointViewer {
                 });
             }
             AppState::FirstLoad => {
+                if let Some(frames) = self.frames.lock().as_ref() {
+                    self.channels = frames
+                        .iter()
+                        .map(|(channel, _, _)| *channel)
+                        .collect::<BTreeSet<_>>()
+                        .into_iter()
+                        .collect();
+                }
                 *self.state.lock() = AppState::Interactive;
             }
             AppState::Interactive => {
-                if let Some(chunks) = self.chunks.lock().as_ref() {
-                    ctx.input(|i| {
-                        if i.key_pressed(eframe::egui::Key::ArrowRight)
-                            && self.current_chunk < chunks.len() - 1
-                        {
-                            self.current_chunk += 1;
-                        }
-                        if i.key_pressed(eframe::egui::Key::ArrowLeft) && self.current_chunk > 0 {
-                            self.current_chunk -= 1;
-                        }
-                    });
+                let frames = Arc::clone(&self.frames);
+                let frames_guard = frames.lock();
+                if let Some(frames) = frames_guard.as_ref() {
+                    let chunks_count = self.chunks_count(frames);
+                    self.current_chunk = self.current_chunk.min(chunks_count - 1);
+
+                    // arrows are used by text inputs when they are focused
+                    let (right, left) = if ctx.wants_keyboard_input() {
+                        (false, false)
+                    } else {
+                        ctx.input(|i| {
+                            (
+                                i.key_pressed(eframe::egui::Key::ArrowRight),
+                                i.key_pressed(eframe::egui::Key::ArrowLeft),
+                            )
+                        })
+                    };
+                    if right {
+                        self.step(frames, true);
+                    }
+                    if left {
+                        self.step(frames, false);
+                    }
 
                     egui::CentralPanel::default().show(ctx, |ui| {
                         #[cfg(not(target_arch = "wasm32"))]
@@ -122,36 +585,66 @@ impl eframe::App for PointViewer {
                             .as_f64()
                             .unwrap() as f32;
 
+                        self.window_controls(ui, frames);
+
+                        let channels = self.channels.clone();
+                        self.selection.input(ui, &channels);
+
+                        self.jump_controls(ui, frames);
+
+                        self.overview(ui, frames);
+
                         ui.style_mut().spacing.slider_width = width - 150.0;
 
+                        let (start_ns, window_ns) = (self.start_ns as f64, self.window_ns as f64);
                         ui.horizontal(|ui| {
                             ui.add(
-                                egui::Slider::new(&mut self.current_chunk, 0..=chunks.len() - 1)
-                                    .suffix(" ms")
-                                    .step_by(1.0),
-                            );
-                            if ui.button("<").clicked() && self.current_chunk > 0 {
-                                self.current_chunk -= 1;
+                                egui::Slider::new(&mut self.current_chunk, 0..=chunks_count - 1)
+                                    .step_by(1.0)
+                                    .custom_formatter(move |chunk, _| {
+                                        format_time(start_ns + chunk * window_ns)
+                                    })
+                                    .custom_parser(move |text| {
+                                        parse_time(text).map(|time| {
+                                            ((time - start_ns) / window_ns).floor().max(0.0)
+                                        })
+                                    }),
+                            )
+                            .on_hover_text("Начало окна от начала точки");
+                            if ui
+                                .button("<")
+                                .on_hover_text("Предыдущее окно с кадрами (←)")
+                                .clicked()
+                            {
+                                self.step(frames, false);
                             }
-                            if ui.button(">").clicked() && self.current_chunk < chunks.len() - 1 {
-                                self.current_chunk += 1;
+                            if ui
+                                .button(">")
+                                .on_hover_text("Следующее окно с кадрами (→)")
+                                .clicked()
+                            {
+                                self.step(frames, true);
                             }
                         });
 
+                        let chunk_start = self.chunk_start(self.current_chunk);
                         egui_plot::Plot::new("waveforms")
                             .legend(Legend::default())
                             .x_axis_formatter(|GridMark { value, .. }, _| {
                                 format!("{value:.3} μs")
                             })
                             .show(ui, |plot_ui| {
-                                for (ch_num, offset, waveform) in chunks[self.current_chunk].clone()
+                                for (ch_num, time, waveform) in self
+                                    .chunk(frames, self.current_chunk)
+                                    .iter()
+                                    .filter(|(ch_num, _, _)| self.selection.is_visible(*ch_num))
                                 {
-                                    waveform.draw_egui(
+                                    waveform.clone().draw_egui(
                                         plot_ui,
                                         Some(&format!("ch #{}", ch_num + 1)),
-                                        Some(color_for_index((ch_num) as usize)),
+                                        Some(color_for_index((*ch_num) as usize)),
                                         None,
-                                        Some(offset),
+                                        Some((time - chunk_start) as i64),
                                     );
                                 }
                             });
@@ -161,3 +654,47 @@ impl eframe::App for PointViewer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_time_units() {
+        assert_eq!(format_time(999.0), "999 ns");
+        assert_eq!(format_time(1_500.0), "1.500 μs");
+        assert_eq!(format_time(2_000_000.0), "2.000 ms");
+        assert_eq!(format_time(3_250_000_000.0), "3.250 s");
+    }
+
+    #[test]
+    fn parse_time_units() {
+        assert_eq!(parse_time("10 ns"), Some(10.0));
+        assert_eq!(parse_time("2us"), Some(2e3));
+        assert_eq!(parse_time("2 μs"), Some(2e3));
+        assert_eq!(parse_time("1.5 ms"), Some(1.5e6));
+        assert_eq!(parse_time(" 3 s "), Some(3e9));
+        assert_eq!(parse_time("1e3 ns"), Some(1e3));
+    }
+
+    #[test]
+    fn parse_time_defaults_to_ms() {
+        assert_eq!(parse_time("5"), Some(5e6));
+        assert_eq!(parse_time("0.25"), Some(0.25e6));
+    }
+
+    #[test]
+    fn parse_time_rejects_garbage() {
+        assert_eq!(parse_time(""), None);
+        assert_eq!(parse_time("ms"), None);
+        assert_eq!(parse_time("5 min"), None);
+        assert_eq!(parse_time("abc"), None);
+    }
+
+    #[test]
+    fn parse_format_round_trip() {
+        for time in [12.0, 3_500.0, 7_250_000.0, 12_500_000_000.0] {
+            assert_eq!(parse_time(&format_time(time)), Some(time));
+        }
+    }
+}
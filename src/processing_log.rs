@@ -0,0 +1,243 @@
+//! Per-file progress of the processing runs.
+//!
+//! Every run of [DataViewerApp::process_points](crate::app::DataViewerApp) resets the log. Files
+//! go through `queued → loading → processing → done/failed` stages (or straight to `cached` if
+//! the point is up to date or its histogram is rebuilt from the [EventsCache](crate::events_cache::EventsCache)).
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use egui::{Color32, Ui};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStage {
+    Queued,
+    /// Modification time check and (in `--server` mode) point download.
+    Loading,
+    Processing,
+    Done,
+    Failed,
+    Cached,
+}
+
+impl FileStage {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Done | Self::Failed | Self::Cached)
+    }
+
+    fn label(self) -> (&'static str, Color32) {
+        match self {
+            Self::Queued => ("queued", Color32::GRAY),
+            Self::Loading => ("loading", Color32::LIGHT_BLUE),
+            Self::Processing => ("processing", Color32::YELLOW),
+            Self::Done => ("done", Color32::GREEN),
+            Self::Failed => ("failed", Color32::RED),
+            Self::Cached => ("cached", Color32::DARK_GREEN),
+        }
+    }
+}
+
+/// Time between two instants (in seconds).
+fn seconds(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_milliseconds() as f64 * 1e-3
+}
+
+/// Human readable duration (`12.3 s`, `4m 05s`, `1h 02m`).
+fn format_duration(seconds: f64) -> String {
+    let whole = seconds.round() as u64;
+    if seconds < 60.0 {
+        format!("{seconds:.1} s")
+    } else if whole < 3600 {
+        format!("{}m {:02}s", whole / 60, whole % 60)
+    } else {
+        format!("{}h {:02}m", whole / 3600, whole % 3600 / 60)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileProgress {
+    pub stage: FileStage,
+    /// Start of the loading stage.
+    loading: Option<DateTime<Utc>>,
+    /// Start of the wait for a web worker (excluded from the loading stage).
+    queued: Option<DateTime<Utc>>,
+    /// Start of the processing stage.
+    processing: Option<DateTime<Utc>>,
+    finished: Option<DateTime<Utc>>,
+}
+
+impl FileProgress {
+    /// Duration of the loading stage (in seconds, up to now if it is not finished).
+    pub fn load_time(&self) -> Option<f64> {
+        let end = self
+            .processing
+            .or(self.finished)
+            .or(self.queued)
+            .unwrap_or_else(Utc::now);
+        self.loading.map(|loading| seconds(loading, end))
+    }
+
+    /// Duration of the processing stage (in seconds, up to now if it is not finished).
+    pub fn process_time(&self) -> Option<f64> {
+        let end = self.finished.unwrap_or_else(Utc::now);
+        self.processing.map(|processing| seconds(processing, end))
+    }
+
+    fn total_time(&self) -> f64 {
+        self.load_time().unwrap_or_default() + self.process_time().unwrap_or_default()
+    }
+}
+
+/// Progress of the last processing run.
+#[derive(Debug, Default)]
+pub struct ProcessingLog {
+    started: Option<DateTime<Utc>>,
+    finished: Option<DateTime<Utc>>,
+    files: BTreeMap<String, FileProgress>,
+    /// Sort files by total time (descending) instead of name.
+    slowest_first: bool,
+}
+
+impl ProcessingLog {
+    /// Resets the log for a new run of `files`.
+    pub fn start(&mut self, files: &[String]) {
+        self.started = Some(Utc::now());
+        self.finished = None;
+        self.files = files
+            .iter()
+            .map(|filepath| {
+                (
+                    filepath.clone(),
+                    FileProgress {
+                        stage: FileStage::Queued,
+                        loading: None,
+                        queued: None,
+                        processing: None,
+                        finished: None,
+                    },
+                )
+            })
+            .collect();
+    }
+
+    /// Moves file to the `stage` (files not in the current run are ignored).
+    pub fn set_stage(&mut self, filepath: &str, stage: FileStage) {
+        let now = Utc::now();
+        let Some(file) = self.files.get_mut(filepath) else {
+            return;
+        };
+        match stage {
+            // loaded file is queued again while it waits for a worker
+            FileStage::Queued => {
+                if file.loading.is_some() {
+                    file.queued = Some(now);
+                }
+            }
+            FileStage::Loading => match (file.loading, file.queued.take()) {
+                (Some(loading), Some(queued)) => file.loading = Some(loading + (now - queued)),
+                (Some(_), None) => {}
+                (None, _) => file.loading = Some(now),
+            },
+            FileStage::Processing => file.processing = Some(now),
+            FileStage::Done | FileStage::Failed | FileStage::Cached => file.finished = Some(now),
+        }
+        file.stage = stage;
+
+        if self.files.values().all(|file| file.stage.is_finished()) {
+            self.finished = Some(now);
+        }
+    }
+
+    fn finished_count(&self) -> usize {
+        self.files
+            .values()
+            .filter(|file| file.stage.is_finished())
+            .count()
+    }
+
+    /// Throughput (files/s) and ETA (in seconds) of the run.
+    ///
+    /// Cached files are finished almost instantly, so only processed (done or failed) files are
+    /// counted in the throughput. Returns `None` until the first file is processed.
+    pub fn estimate(&self) -> Option<(f64, f64)> {
+        let elapsed = seconds(self.started?, self.finished.unwrap_or_else(Utc::now));
+        let processed = self
+            .files
+            .values()
+            .filter(|file| matches!(file.stage, FileStage::Done | FileStage::Failed))
+            .count();
+        if processed == 0 || elapsed <= 0.0 {
+            return None;
+        }
+        let throughput = processed as f64 / elapsed;
+        Some((
+            throughput,
+            (self.files.len() - self.finished_count()) as f64 / throughput,
+        ))
+    }
+
+    /// Draws run summary and per-file table.
+    pub fn input(&mut self, ui: &mut Ui) {
+        let Some(started) = self.started else {
+            return;
+        };
+
+        let mut title = format!("processing: {}/{}", self.finished_count(), self.files.len());
+        if let Some((throughput, eta)) = self.estimate() {
+            title.push_str(&format!(", {throughput:.2} files/s"));
+            if self.finished.is_none() {
+                title.push_str(&format!(", ETA {}", format_duration(eta)));
+            }
+        }
+
+        egui::CollapsingHeader::new(title)
+            .id_salt("processing_log")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "elapsed: {}",
+                        format_duration(seconds(started, self.finished.unwrap_or_else(Utc::now)))
+                    ));
+                    ui.checkbox(&mut self.slowest_first, "slowest first")
+                        .on_hover_text(
+                            "Сортировать файлы по суммарному времени загрузки и обработки",
+                        );
+                });
+
+                let mut files = self.files.iter().collect::<Vec<_>>();
+                if self.slowest_first {
+                    files.sort_by(|(_, file_1), (_, file_2)| {
+                        file_2.total_time().total_cmp(&file_1.total_time())
+                    });
+                }
+
+                egui::ScrollArea::vertical()
+                    .id_salt("processing_log_scroll")
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("processing_log_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.label("file");
+                                ui.label("state");
+                                ui.label("load");
+                                ui.label("process");
+                                ui.end_row();
+
+                                let time_label = |ui: &mut Ui, time: Option<f64>| {
+                                    ui.label(time.map_or("-".to_owned(), format_duration));
+                                };
+
+                                for (filepath, file) in files {
+                                    let name = filepath.rsplit('/').next().unwrap_or(filepath);
+                                    ui.label(name).on_hover_text(filepath);
+                                    let (stage, color) = file.stage.label();
+                                    ui.colored_label(color, stage);
+                                    time_label(ui, file.load_time());
+                                    time_label(ui, file.process_time());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+    }
+}
@@ -0,0 +1,92 @@
+//! Effective time and count rates of the processed points.
+use processing::{preprocess::Preprocess, viewer::PointState};
+
+use crate::{
+    dead_time::DeadTimeParams, event_filter::EventFilter, events_cache::ChannelCounts,
+    time_window::TimeWindow,
+};
+
+/// Point effective time in seconds.
+///
+/// If `cut_bad_blocks` is set bad blocks are excluded, otherwise full acquisition time is used.
+pub fn effective_time(preprocess: &Preprocess, cut_bad_blocks: bool) -> f64 {
+    if cut_bad_blocks {
+        preprocess.effective_time() as f64 * 1e-9
+    } else {
+        preprocess.acquisition_time as f64 * 1e-9
+    }
+}
+
+/// Point effective time in seconds reduced to the `time_window` and the time-in-point window
+/// of the `filter` (see [TimeWindow::effective_time]).
+pub fn filtered_time(
+    preprocess: &Preprocess,
+    cut_bad_blocks: bool,
+    time_window: &TimeWindow,
+    filter: &EventFilter,
+) -> f64 {
+    if !filter.time_enabled {
+        return time_window.effective_time(preprocess, cut_bad_blocks);
+    }
+    let window = if time_window.enabled {
+        // both windows cut the point, so only their intersection is left
+        TimeWindow {
+            enabled: true,
+            start: time_window.start.max(filter.time_from),
+            end: time_window.end.min(filter.time_to),
+        }
+    } else {
+        TimeWindow {
+            enabled: true,
+            start: filter.time_from,
+            end: filter.time_to,
+        }
+    };
+    window.effective_time(preprocess, cut_bad_blocks)
+}
+
+/// Count rate (in Hz) and effective time (in s) in `f32` precision, as PPV/PPT exports were
+/// written before dead-time corrections (exports without correction stay unchanged).
+pub fn legacy_rate(counts: usize, effective_time: f64) -> (f32, f32) {
+    let effective_time = (effective_time * 1e9).round() as f32 * 1e-9;
+    (counts as f32 / effective_time, effective_time)
+}
+
+/// Measured and dead-time corrected count rates (in Hz) of a processed point.
+///
+/// `channel_counts` are unfiltered counts of the point used for dead-time corrections
+/// (see [DeadTimeParams::corrected_rate]).
+///
+/// Returns `None` if point is not processed yet or nothing is left of its effective time.
+pub fn point_rates(
+    cache: &PointState,
+    channel_counts: Option<&ChannelCounts>,
+    cut_bad_blocks: bool,
+    dead_time: &DeadTimeParams,
+    time_window: &TimeWindow,
+    filter: &EventFilter,
+) -> Option<(f64, f64)> {
+    if let PointState {
+        counts: Some(counts),
+        preprocess: Some(preprocess),
+        ..
+    } = cache
+    {
+        let effective_time = filtered_time(preprocess, cut_bad_blocks, time_window, filter);
+        if effective_time <= 0.0 {
+            return None;
+        }
+        let rate = *counts as f64 / effective_time;
+        let corrected = match &cache.histogram {
+            Some(histogram) if dead_time.enabled() => {
+                // counts of all events are taken in the time window only
+                let counts_time = time_window.effective_time(preprocess, cut_bad_blocks);
+                dead_time.corrected_rate(histogram, effective_time, channel_counts, counts_time)
+            }
+            _ => rate,
+        };
+        Some((rate, corrected))
+    } else {
+        None
+    }
+}
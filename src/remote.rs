@@ -0,0 +1,268 @@
+//! Client of the numass-server HTTP API (native `--server` mode).
+//!
+//! Uses the same endpoints as the web version: directories are listed by `api/root` and metas are
+//! loaded by `api/meta`. Points are downloaded from the server `files` route into the cache
+//! directory and processed locally. Annotations are not available (see [crate::annotations::available]).
+use std::{
+    future::Future,
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::SystemTime,
+};
+
+use processing::{
+    numass::NumassMeta,
+    storage::{FSRepr, LoadState},
+};
+use serde::Serialize;
+
+static SERVER: OnceLock<RemoteServer> = OnceLock::new();
+/// Counter of the downloads (makes names of the partial files unique).
+static DOWNLOADS: AtomicU64 = AtomicU64::new(0);
+
+/// Switches data access of the process to the numass-server at `url`.
+///
+/// # Arguments
+/// * `url` - server address (e.g. `http://localhost:8085`).
+/// * `cache_directory` - directory for the downloaded points (temporary directory if None).
+///
+pub fn connect(url: &str, cache_directory: Option<PathBuf>) {
+    let server = RemoteServer {
+        base: url.trim_end_matches('/').to_owned(),
+        cache_directory: cache_directory
+            .unwrap_or_else(|| std::env::temp_dir().join("numass-viewers")),
+        client: reqwest::Client::new(),
+    };
+    if SERVER.set(server).is_err() {
+        tracing::warn!("already connected to {}", SERVER.get().unwrap().base);
+    }
+}
+
+/// Server connected by [connect] (None - local data).
+pub fn server() -> Option<&'static RemoteServer> {
+    SERVER.get()
+}
+
+#[derive(Serialize)]
+struct PathQuery<'a> {
+    filepath: &'a Path,
+}
+
+fn entry_path(entry: &FSRepr) -> &Path {
+    match entry {
+        FSRepr::File { path, .. } | FSRepr::Directory { path, .. } => path,
+    }
+}
+
+pub struct RemoteServer {
+    base: String,
+    cache_directory: PathBuf,
+    client: reqwest::Client,
+}
+
+impl RemoteServer {
+    fn url(&self, api: &str, path: &Path) -> String {
+        let query = serde_qs::to_string(&PathQuery { filepath: path }).unwrap();
+        format!("{}/{api}?{query}", self.base)
+    }
+
+    /// Url of the point in the server `files` route (path components are percent-encoded).
+    fn file_url(&self, path: &Path) -> Option<String> {
+        let mut url = reqwest::Url::parse(&format!("{}/files", self.base)).ok()?;
+        url.path_segments_mut()
+            .ok()?
+            .extend(path.components().filter_map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            }));
+        Some(url.into())
+    }
+
+    async fn get(&self, url: String) -> Option<reqwest::Response> {
+        match self.client.get(&url).send().await {
+            Ok(resp) if resp.status().is_success() => Some(resp),
+            Ok(resp) => {
+                tracing::warn!("{url}: server responded with {}", resp.status());
+                None
+            }
+            Err(err) => {
+                tracing::warn!("{url}: {err}");
+                None
+            }
+        }
+    }
+
+    /// Loads directory listing (server root if `path` is None).
+    pub async fn load_root(&self, path: Option<&Path>) -> Option<FSRepr> {
+        let url = match path {
+            Some(path) => self.url("api/root", path),
+            None => format!("{}/api/root", self.base),
+        };
+        self.get(url).await?.json::<FSRepr>().await.ok()
+    }
+
+    /// Remote version of [FSRepr::expand_reccurently] (loads directories marked as [LoadState::NeedLoad]).
+    pub fn expand<'a>(
+        &'a self,
+        entry: &'a mut FSRepr,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            match entry {
+                FSRepr::Directory { load_state, .. } if *load_state == LoadState::NeedLoad => {
+                    let path = entry_path(entry).to_owned();
+                    if let Some(loaded) = self.load_root(Some(&path)).await {
+                        *entry = loaded;
+                    } else if let FSRepr::Directory { load_state, .. } = entry {
+                        *load_state = LoadState::NotLoaded;
+                    }
+                }
+                FSRepr::Directory { children, .. } => {
+                    for child in children {
+                        self.expand(child).await;
+                    }
+                }
+                FSRepr::File { .. } => {}
+            }
+        })
+    }
+
+    /// Remote version of [FSRepr::update_reccurently] (reloads loaded directories keeping expanded subdirectories).
+    pub fn update<'a>(
+        &'a self,
+        entry: &'a mut FSRepr,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            let FSRepr::Directory {
+                path,
+                children,
+                load_state,
+                ..
+            } = entry
+            else {
+                return;
+            };
+            if *load_state == LoadState::NotLoaded {
+                return;
+            }
+            let Some(FSRepr::Directory {
+                children: fresh, ..
+            }) = self.load_root(Some(path.as_path())).await
+            else {
+                return;
+            };
+
+            let mut old = std::mem::replace(children, fresh);
+            for child in children.iter_mut() {
+                let Some(idx) = old
+                    .iter()
+                    .position(|old| entry_path(old) == entry_path(child))
+                else {
+                    continue;
+                };
+                let mut previous = old.swap_remove(idx);
+                if let FSRepr::Directory { .. } = previous {
+                    self.update(&mut previous).await;
+                    *child = previous;
+                }
+            }
+        })
+    }
+
+    pub async fn load_meta(&self, path: &Path) -> Option<NumassMeta> {
+        self.get(self.url("api/meta", path))
+            .await?
+            .json::<NumassMeta>()
+            .await
+            .ok()
+    }
+
+    /// Returns local copy of the point (downloaded on the first request and if it was
+    /// modified on the server after the download).
+    pub async fn fetch_point(&self, path: &Path) -> Option<PathBuf> {
+        let modified = self.server_modified_time(path).await;
+        self.fetch_point_modified(path, modified).await
+    }
+
+    /// Same as [RemoteServer::fetch_point] with already known server modification time of the point
+    /// (see [RemoteServer::modified_time]), so the server is not asked for it again.
+    pub async fn fetch_point_modified(
+        &self,
+        path: &Path,
+        modified: Option<SystemTime>,
+    ) -> Option<PathBuf> {
+        let local = self
+            .cache_directory
+            .join(path.strip_prefix("/").unwrap_or(path));
+        if let Ok(metadata) = tokio::fs::metadata(&local).await {
+            match (modified, metadata.modified().ok()) {
+                (Some(modified), Some(downloaded)) if modified > downloaded => {}
+                (None, _) => {
+                    tracing::warn!(
+                        "{path:?}: modification time on the server is unknown, using downloaded copy"
+                    );
+                    return Some(local);
+                }
+                _ => return Some(local),
+            }
+        }
+
+        let content = self.get(self.file_url(path)?).await?.bytes().await.ok()?;
+
+        // write to a temporary file first so interrupted downloads are not taken for points
+        // (name is unique, so simultaneous downloads of the same point do not collide)
+        let partial = local.with_file_name(format!(
+            "{}.{}-{}.part",
+            local.file_name()?.to_string_lossy(),
+            std::process::id(),
+            DOWNLOADS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = async {
+            tokio::fs::create_dir_all(local.parent().unwrap()).await?;
+            tokio::fs::write(&partial, content).await?;
+            tokio::fs::rename(&partial, &local).await
+        }
+        .await;
+        match result {
+            Ok(_) => Some(local),
+            Err(err) => {
+                tracing::warn!("can't save {path:?} to {local:?}: {err}");
+                let _ = tokio::fs::remove_file(&partial).await;
+                None
+            }
+        }
+    }
+
+    /// Modification time of the point on the server (`Last-Modified` of the `files` route).
+    async fn server_modified_time(&self, path: &Path) -> Option<SystemTime> {
+        let url = self.file_url(path)?;
+        match self.client.head(&url).send().await {
+            Ok(resp) if resp.status().is_success() => resp
+                .headers()
+                .get(reqwest::header::LAST_MODIFIED)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| chrono::DateTime::parse_from_rfc2822(value).ok())
+                .map(SystemTime::from),
+            Ok(resp) => {
+                tracing::warn!("{url}: server responded with {}", resp.status());
+                None
+            }
+            Err(err) => {
+                tracing::warn!("{url}: {err}");
+                None
+            }
+        }
+    }
+
+    /// Modification time of the point (local copy modification time if server does not report it).
+    pub async fn modified_time(&self, path: &Path) -> Option<SystemTime> {
+        if let Some(modified) = self.server_modified_time(path).await {
+            return Some(modified);
+        }
+        let local = self.fetch_point_modified(path, None).await?;
+        tokio::fs::metadata(local).await.ok()?.modified().ok()
+    }
+}
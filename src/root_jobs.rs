@@ -0,0 +1,235 @@
+//! Queue of `convert-to-root` jobs with bounded concurrency (native only).
+use std::{collections::BTreeSet, path::PathBuf, sync::Arc};
+
+use egui::{mutex::Mutex, Color32, Ui};
+use processing::{postprocess::PostProcessParams, process::ProcessParams};
+use tokio::{process::Command, sync::Semaphore};
+
+/// Name of the converter binary (must be in PATH).
+pub const CONVERTER: &str = "convert-to-root";
+
+/// How many lines of converter stderr are kept for a failed job.
+const STDERR_TAIL_LINES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed {
+        /// Exit code (None if process was not started or killed by signal).
+        code: Option<i32>,
+        /// Error message or tail of the converter stderr.
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct ConversionJob {
+    pub source: String,
+    pub output: PathBuf,
+    pub status: JobStatus,
+}
+
+/// Shared state of the current conversion run.
+#[derive(Debug, Default)]
+pub struct ConversionQueue {
+    pub jobs: Vec<ConversionJob>,
+    /// Id of the current run (results of the previous runs are ignored).
+    run: u64,
+    /// Output folder of a new run is being chosen (new runs are refused).
+    pub starting: bool,
+}
+
+impl ConversionQueue {
+    /// Returns true if some jobs are not finished yet.
+    pub fn running(&self) -> bool {
+        self.jobs
+            .iter()
+            .any(|job| matches!(job.status, JobStatus::Queued | JobStatus::Running))
+    }
+
+    /// Sets status of the `source` job of the `run` (ignored if the run was replaced).
+    fn set_status(&mut self, run: u64, source: &str, status: JobStatus) {
+        if run != self.run {
+            return;
+        }
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.source == source) {
+            job.status = status;
+        }
+    }
+
+    /// Number of finished (successfully or not) jobs.
+    pub fn finished(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| matches!(job.status, JobStatus::Done | JobStatus::Failed { .. }))
+            .count()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &ConversionJob> {
+        self.jobs
+            .iter()
+            .filter(|job| matches!(job.status, JobStatus::Failed { .. }))
+    }
+
+    /// Draws progress (while running) or a summary of the last run.
+    ///
+    /// Returns true if user dismissed the summary.
+    pub fn show(&self, ui: &mut Ui) -> bool {
+        if self.jobs.is_empty() {
+            return false;
+        }
+
+        if self.running() {
+            ui.horizontal(|ui| {
+                ui.label(format!("root: {}/{}", self.finished(), self.jobs.len()));
+                ui.spinner();
+            });
+            return false;
+        }
+
+        let failed = self.failed().collect::<Vec<_>>();
+        let mut dismissed = false;
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "root: {} done, {} failed",
+                self.jobs.len() - failed.len(),
+                failed.len()
+            ));
+            dismissed = ui.button("x").clicked();
+        });
+
+        if !failed.is_empty() {
+            egui::CollapsingHeader::new("root conversion errors")
+                .id_salt("root_jobs_failed")
+                .show(ui, |ui| {
+                    for job in failed {
+                        if let JobStatus::Failed { code, message } = &job.status {
+                            let code = code.map_or("-".to_owned(), |code| code.to_string());
+                            ui.colored_label(Color32::RED, format!("{} (exit {code})", job.source))
+                                .on_hover_text(message);
+                        }
+                    }
+                });
+        }
+
+        dismissed
+    }
+}
+
+/// Starts converting `sources` (point, output file) with at most `concurrency` processes at once.
+///
+/// Points are resolved by [point_path](crate::point_path), so in native `--server` mode they are
+/// downloaded before conversion.
+///
+/// Previous run results in `queue` are replaced. Progress and results are written to `queue`.
+/// Outputs which already exist (or are repeated in `sources`) are not overwritten,
+/// such jobs are marked as failed without running the converter.
+///
+/// Returns false (and does nothing) if the previous run is not finished yet.
+pub fn start_conversion(
+    queue: Arc<Mutex<ConversionQueue>>,
+    sources: Vec<(String, PathBuf)>,
+    process: ProcessParams,
+    post_process: PostProcessParams,
+    concurrency: usize,
+) -> bool {
+    let (run, jobs) = {
+        let mut outputs = BTreeSet::new();
+        let mut queue = queue.lock();
+        if queue.running() {
+            tracing::warn!("previous conversion is not finished, new one is refused");
+            return false;
+        }
+        queue.run += 1;
+        queue.jobs = sources
+            .into_iter()
+            .map(|(source, output)| {
+                let status = if output.exists() || !outputs.insert(output.clone()) {
+                    JobStatus::Failed {
+                        code: None,
+                        message: format!("{output:?} already exists, refused to overwrite"),
+                    }
+                } else {
+                    JobStatus::Queued
+                };
+                ConversionJob {
+                    source,
+                    output,
+                    status,
+                }
+            })
+            .collect();
+        (queue.run, queue.jobs.clone())
+    };
+
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let process = serde_json::to_string(&process).unwrap();
+    let post_process = serde_json::to_string(&post_process).unwrap();
+
+    for job in jobs {
+        if job.status != JobStatus::Queued {
+            continue;
+        }
+
+        let queue = Arc::clone(&queue);
+        let semaphore = Arc::clone(&semaphore);
+        let process = process.clone();
+        let post_process = post_process.clone();
+
+        tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await.unwrap();
+            queue
+                .lock()
+                .set_status(run, &job.source, JobStatus::Running);
+
+            // in `--server` mode the converter gets the downloaded copy of the point
+            let Some(local) = crate::point_path(PathBuf::from(&job.source)).await else {
+                let status = JobStatus::Failed {
+                    code: None,
+                    message: format!("can't load {}", job.source),
+                };
+                tracing::error!("{} conversion failed: can't load the point", job.source);
+                queue.lock().set_status(run, &job.source, status);
+                return;
+            };
+
+            let output = Command::new(CONVERTER)
+                .arg(&local)
+                .arg("--process")
+                .arg(process)
+                .arg("--postprocess")
+                .arg(post_process)
+                .arg("--output")
+                .arg(&job.output)
+                .kill_on_drop(true)
+                .output()
+                .await;
+
+            let status = match output {
+                Ok(output) if output.status.success() => JobStatus::Done,
+                Ok(output) => {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    let lines = stderr.lines().collect::<Vec<_>>();
+                    JobStatus::Failed {
+                        code: output.status.code(),
+                        message: lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n"),
+                    }
+                }
+                Err(err) => JobStatus::Failed {
+                    code: None,
+                    message: format!("can't start {CONVERTER}: {err}"),
+                },
+            };
+
+            if let JobStatus::Failed { message, .. } = &status {
+                tracing::error!("{} conversion failed: {message}", job.source);
+            }
+            queue.lock().set_status(run, &job.source, status);
+        });
+    }
+
+    true
+}
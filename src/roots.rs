@@ -0,0 +1,116 @@
+//! Recently opened roots and bookmarked directories.
+//!
+//! History is stored in `~/.config/numass-viewers/roots.json` (native) or in the browser local storage (web).
+use std::path::{Path, PathBuf};
+
+use egui::Ui;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{load_setting, save_setting};
+
+/// How many recently opened roots are kept.
+const MAX_RECENT: usize = 10;
+
+/// Name of the history setting (see [crate::settings]).
+const SETTING: &str = "roots";
+
+/// Action requested from the roots menu (see [RootsHistory::menu]).
+#[derive(Debug, Clone, PartialEq)]
+pub enum RootsAction {
+    Open(PathBuf),
+    Close(PathBuf),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RootsHistory {
+    /// Recently opened roots (most recent first).
+    pub recent: Vec<PathBuf>,
+    pub bookmarks: Vec<PathBuf>,
+}
+
+impl RootsHistory {
+    /// Loads stored history (empty if not stored yet or broken).
+    pub fn load() -> Self {
+        load_setting(SETTING)
+    }
+
+    fn save(&self) {
+        save_setting(SETTING, self);
+    }
+
+    /// Moves `path` to the top of the recent roots.
+    pub fn push_recent(&mut self, path: &Path) {
+        self.recent.retain(|recent| recent != path);
+        self.recent.insert(0, path.to_owned());
+        self.recent.truncate(MAX_RECENT);
+        self.save();
+    }
+
+    pub fn toggle_bookmark(&mut self, path: &Path) {
+        if self.bookmarks.iter().any(|bookmark| bookmark == path) {
+            self.bookmarks.retain(|bookmark| bookmark != path);
+        } else {
+            self.bookmarks.push(path.to_owned());
+        }
+        self.save();
+    }
+
+    /// Draws roots dropdown (opened roots, bookmarks and recent roots).
+    ///
+    /// # Arguments
+    /// * `opened` - paths of the currently opened roots.
+    ///
+    pub fn menu(&mut self, ui: &mut Ui, opened: &[PathBuf]) -> Option<RootsAction> {
+        let mut action = None;
+        let mut toggle_bookmark = None;
+
+        ui.menu_button("⏷", |ui| {
+            if !opened.is_empty() {
+                ui.label("opened:");
+                for path in opened {
+                    ui.horizontal(|ui| {
+                        let bookmarked = self.bookmarks.contains(path);
+                        if ui
+                            .small_button(if bookmarked { "★" } else { "☆" })
+                            .on_hover_text("Добавить/удалить закладку")
+                            .clicked()
+                        {
+                            toggle_bookmark = Some(path.clone());
+                        }
+                        if ui.small_button("✖").on_hover_text("Закрыть").clicked() {
+                            action = Some(RootsAction::Close(path.clone()));
+                            ui.close_menu();
+                        }
+                        ui.label(path.to_string_lossy());
+                    });
+                }
+                ui.separator();
+            }
+
+            for (title, paths) in [("bookmarks:", &self.bookmarks), ("recent:", &self.recent)] {
+                let closed = paths
+                    .iter()
+                    .filter(|path| !opened.contains(path))
+                    .collect::<Vec<_>>();
+                if closed.is_empty() {
+                    continue;
+                }
+                ui.label(title);
+                for path in closed {
+                    if ui.button(path.to_string_lossy()).clicked() {
+                        action = Some(RootsAction::Open(path.clone()));
+                        ui.close_menu();
+                    }
+                }
+            }
+        })
+        .response
+        .on_hover_text("Открытые, избранные и недавние папки");
+
+        if let Some(path) = toggle_bookmark {
+            self.toggle_bookmark(&path);
+        }
+
+        action
+    }
+}
@@ -0,0 +1,51 @@
+//! Persistent settings of the viewers.
+//!
+//! Setting `name` is stored in `~/.config/numass-viewers/<name>.json` (native) or in the browser
+//! local storage under the `numass-viewers-<name>` key (web).
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+fn config_path(name: &str) -> Option<std::path::PathBuf> {
+    home::home_dir().map(|home| home.join(format!(".config/numass-viewers/{name}.json")))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn storage_key(name: &str) -> String {
+    format!("numass-viewers-{name}")
+}
+
+/// Loads setting `name` (default if not stored yet or broken).
+pub fn load_setting<T: DeserializeOwned + Default>(name: &str) -> T {
+    #[cfg(not(target_arch = "wasm32"))]
+    let content = config_path(name).and_then(|path| std::fs::read_to_string(path).ok());
+    #[cfg(target_arch = "wasm32")]
+    let content = eframe::web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item(&storage_key(name)).ok().flatten());
+
+    content
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Stores `value` as setting `name` (errors are logged).
+pub fn save_setting<T: Serialize>(name: &str, value: &T) {
+    let content = serde_json::to_string_pretty(value).unwrap();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = config_path(name) {
+        let result = std::fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| std::fs::write(&path, content));
+        if let Err(err) = result {
+            tracing::warn!("can't save {name} settings to {path:?}: {err}");
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    if let Some(storage) =
+        eframe::web_sys::window().and_then(|window| window.local_storage().ok().flatten())
+    {
+        if storage.set_item(&storage_key(name), &content).is_err() {
+            tracing::warn!("can't save {name} settings to local storage");
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Processing of a time sub-interval of the points.
+//!
+//! Events outside of the window are dropped right after post processing and the effective time
+//! of the point is calculated only over the window (bad blocks inside the window are excluded
+//! if `cut_bad_blocks` is set).
+use egui::Ui;
+use egui_plot::{Line, PlotUi};
+use processing::{
+    preprocess::{Preprocess, CUTOFF_BIN_SIZE},
+    types::NumassEvents,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::rates::effective_time;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeWindow {
+    pub enabled: bool,
+    /// Window start from the point start (in seconds).
+    pub start: f64,
+    /// Window end from the point start (in seconds).
+    pub end: f64,
+}
+
+impl Default for TimeWindow {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: 0.0,
+            end: 100.0,
+        }
+    }
+}
+
+/// Length of the `[start_1, end_1] ∩ [start_2, end_2]` intersection.
+fn overlap(start_1: f64, end_1: f64, start_2: f64, end_2: f64) -> f64 {
+    (end_1.min(end_2) - start_1.max(start_2)).max(0.0)
+}
+
+impl TimeWindow {
+    /// Window bounds in ns (whole point if disabled).
+    fn bounds_ns(&self) -> (u64, u64) {
+        if self.enabled {
+            ((self.start * 1e9) as u64, (self.end * 1e9) as u64)
+        } else {
+            (0, u64::MAX)
+        }
+    }
+
+    /// Drops events (and frames) outside of the window.
+    pub fn apply(&self, mut events: NumassEvents) -> NumassEvents {
+        if !self.enabled {
+            return events;
+        }
+        let (start, end) = self.bounds_ns();
+        events.retain(|time, frame| {
+            frame.retain(|(offset, _)| (start..=end).contains(&(time + *offset as u64)));
+            !frame.is_empty()
+        });
+        events
+    }
+
+    /// Point effective time in seconds restricted to the window.
+    ///
+    /// # Arguments
+    /// * `preprocess` - preprocess info of the point (acquisition time and bad blocks).
+    /// * `cut_bad_blocks` - exclude bad blocks from the effective time.
+    ///
+    pub fn effective_time(&self, preprocess: &Preprocess, cut_bad_blocks: bool) -> f64 {
+        if !self.enabled {
+            return effective_time(preprocess, cut_bad_blocks);
+        }
+
+        self.window_time(
+            preprocess.acquisition_time as f64 * 1e-9,
+            preprocess
+                .bad_blocks
+                .iter()
+                .filter(|_| cut_bad_blocks)
+                .map(|idx| *idx as u64),
+        )
+    }
+
+    /// Time in seconds of the point (of `acquisition_time` seconds) covered by the window
+    /// without the `bad_blocks` (indexes of [CUTOFF_BIN_SIZE] blocks).
+    fn window_time(&self, acquisition_time: f64, bad_blocks: impl IntoIterator<Item = u64>) -> f64 {
+        let block = CUTOFF_BIN_SIZE as f64 * 1e-9;
+        let mut time = overlap(self.start, self.end, 0.0, acquisition_time);
+        for idx in bad_blocks {
+            let block_start = idx as f64 * block;
+            time -= overlap(
+                self.start,
+                self.end,
+                block_start,
+                (block_start + block).min(acquisition_time),
+            );
+        }
+        time.max(0.0)
+    }
+
+    /// Short description of the window (None if disabled).
+    pub fn summary(&self) -> Option<String> {
+        self.enabled
+            .then(|| format!("t {}..{} s", self.start, self.end))
+    }
+
+    /// Adds description of the enabled window to the plot legend.
+    pub fn legend(&self, plot_ui: &mut PlotUi) {
+        if let Some(summary) = self.summary() {
+            plot_ui.line(Line::new(
+                format!("window: {summary}"),
+                Vec::<[f64; 2]>::new(),
+            ));
+        }
+    }
+
+    /// Draws window editor. Returns updated window.
+    pub fn input(&self, ui: &mut Ui, _ctx: &egui::Context) -> Self {
+        let mut window = *self;
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut window.enabled, "time window:");
+            ui.add_enabled_ui(window.enabled, |ui| {
+                ui.add(
+                    egui::DragValue::new(&mut window.start)
+                        .range(0.0..=window.end)
+                        .suffix(" s"),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut window.end)
+                        .range(window.start..=1e6)
+                        .suffix(" s"),
+                );
+            });
+        })
+        .response
+        .on_hover_text(
+            "Обрабатывать только интервал от начала точки (события и эффективное время всех открытых точек)",
+        );
+
+        window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use processing::types::FrameEvent;
+
+    use super::*;
+
+    const BLOCK: f64 = CUTOFF_BIN_SIZE as f64 * 1e-9;
+
+    fn window(start: f64, end: f64) -> TimeWindow {
+        TimeWindow {
+            enabled: true,
+            start,
+            end,
+        }
+    }
+
+    fn assert_close(time: f64, expected: f64) {
+        assert!((time - expected).abs() < 1e-9, "{time} != {expected}");
+    }
+
+    #[test]
+    fn window_inside_point() {
+        assert_close(window(1.0, 3.0).window_time(10.0, []), 2.0);
+    }
+
+    #[test]
+    fn window_past_point_end() {
+        assert_close(window(5.0, 20.0).window_time(10.0, []), 5.0);
+        assert_close(window(15.0, 20.0).window_time(10.0, []), 0.0);
+    }
+
+    #[test]
+    fn start_after_end() {
+        assert_close(window(3.0, 1.0).window_time(10.0, []), 0.0);
+        assert_close(window(3.0, 1.0).window_time(10.0, [0]), 0.0);
+    }
+
+    #[test]
+    fn bad_blocks_intersection() {
+        // half of the block 1 is inside the window, block 5 is outside
+        let window = window(1.5 * BLOCK, 3.0 * BLOCK);
+        assert_close(window.window_time(10.0 * BLOCK, [1, 5]), BLOCK);
+        // the last block is shorter than the others
+        let whole = TimeWindow {
+            start: 0.0,
+            end: 10.0 * BLOCK,
+            ..window
+        };
+        assert_close(whole.window_time(2.5 * BLOCK, [2]), 2.0 * BLOCK);
+    }
+
+    #[test]
+    fn zero_effective_time() {
+        let window = window(1.5 * BLOCK, 3.0 * BLOCK);
+        assert_close(window.window_time(10.0 * BLOCK, [1, 2]), 0.0);
+    }
+
+    #[test]
+    fn apply_drops_events_outside() {
+        let event = || FrameEvent::Event {
+            channel: 0,
+            amplitude: 10.0,
+            size: 10,
+        };
+        let events = BTreeMap::from([
+            (500_000_000, vec![(0, event())]),
+            (1_500_000_000, vec![(0, event()), (10, event())]),
+            (1_999_999_995, vec![(0, event()), (10, event())]),
+        ]);
+
+        let disabled = TimeWindow::default().apply(events.clone());
+        assert_eq!(disabled.len(), 3);
+
+        let events = window(1.0, 2.0).apply(events);
+        let lengths = events
+            .iter()
+            .map(|(time, frame)| (*time, frame.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(lengths, vec![(1_500_000_000, 2), (1_999_999_995, 1)]);
+    }
+}
@@ -0,0 +1,245 @@
+//! Keyboard navigation and range selection for the file tree widgets.
+use std::{collections::BTreeMap, path::Path};
+
+use egui::{Key, Modifiers, Response, RichText, Ui};
+
+/// How a file checkbox click should be applied.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClickAction {
+    /// Plain click (exclusive if "select single" is enabled).
+    Single,
+    /// Ctrl-click: toggles only the clicked file, other files are kept.
+    Add,
+    /// Shift-click: visible points from the anchor to the clicked one (inclusive)
+    /// should get the clicked point value. Set meta files are not included.
+    Range(Vec<String>),
+}
+
+/// Bulk selection actions (see [TreeSelection::input]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BulkAction {
+    /// Invert selection of the visible files.
+    Invert,
+    /// Deselect all files.
+    SelectNone,
+}
+
+/// Checks if `key` is a set meta file (not a point).
+fn is_set_meta(key: &str) -> bool {
+    let path = Path::new(key);
+    path.ends_with("meta") || path.ends_with("meta.df")
+}
+
+/// Focus and range selection state of a file tree.
+///
+/// Visible files are registered by [TreeSelection::label] while the tree is drawn,
+/// navigation uses the order of the previous frame.
+#[derive(Debug, Default)]
+pub struct TreeSelection {
+    /// File under keyboard cursor.
+    focused: Option<String>,
+    /// Last clicked file (start of the shift-click range).
+    anchor: Option<String>,
+    /// Visible files of the previous frame in display order.
+    visible: Vec<String>,
+    /// Visible files of the current frame.
+    current: Vec<String>,
+    /// Focus was moved by keyboard and should be scrolled to.
+    scroll_to_focused: bool,
+}
+
+impl TreeSelection {
+    /// Must be called before drawing the tree.
+    pub fn begin_frame(&mut self) {
+        self.visible = std::mem::take(&mut self.current);
+    }
+
+    /// Visible files (in display order).
+    pub fn visible(&self) -> &[String] {
+        &self.visible
+    }
+
+    /// Draws file label (highlighted if focused) and registers the file as visible.
+    /// Click on the label moves focus to the file.
+    pub fn label(&mut self, ui: &mut Ui, key: &str, text: &str) -> Response {
+        let focused = self.focused.as_deref() == Some(key);
+        let response = ui.selectable_label(focused, text);
+        self.register(key, response)
+    }
+
+    /// Same as [TreeSelection::label] but the file name is a link to `url` (file meta on the web).
+    pub fn link(&mut self, ui: &mut Ui, key: &str, text: &str, url: impl ToString) -> Response {
+        let mut text = RichText::new(text);
+        if self.focused.as_deref() == Some(key) {
+            text = text.background_color(ui.visuals().selection.bg_fill);
+        }
+        let response = ui.hyperlink_to(text, url);
+        self.register(key, response)
+    }
+
+    fn register(&mut self, key: &str, response: Response) -> Response {
+        self.current.push(key.to_owned());
+
+        let focused = self.focused.as_deref() == Some(key);
+        if focused && self.scroll_to_focused {
+            response.scroll_to_me(None);
+            self.scroll_to_focused = false;
+        }
+        if response.clicked() {
+            // space should toggle the file, not click the label again
+            response.surrender_focus();
+            self.focused = Some(key.to_owned());
+            self.anchor = Some(key.to_owned());
+        }
+        response
+    }
+
+    /// Handles click on the file checkbox.
+    ///
+    /// Shift-click on a set meta file is handled as a plain click.
+    pub fn clicked(&mut self, key: &str, modifiers: Modifiers) -> ClickAction {
+        if modifiers.shift && !is_set_meta(key) {
+            let anchor = self
+                .anchor
+                .as_ref()
+                .and_then(|anchor| self.visible.iter().position(|file| file == anchor));
+            let clicked = self.visible.iter().position(|file| file == key);
+
+            if let (Some(anchor), Some(clicked)) = (anchor, clicked) {
+                self.focused = Some(key.to_owned());
+                return ClickAction::Range(
+                    self.visible[anchor.min(clicked)..=anchor.max(clicked)]
+                        .iter()
+                        .filter(|file| !is_set_meta(file))
+                        .cloned()
+                        .collect(),
+                );
+            }
+        }
+
+        self.focused = Some(key.to_owned());
+        self.anchor = Some(key.to_owned());
+
+        if modifiers.command {
+            ClickAction::Add
+        } else {
+            ClickAction::Single
+        }
+    }
+
+    /// Handles arrow keys (focus movement) and space.
+    ///
+    /// Returns focused file if it should be toggled. Ignored while a text field has focus,
+    /// space is ignored while any widget has focus (it is handled by the widget).
+    pub fn keyboard(&mut self, ctx: &egui::Context) -> Option<String> {
+        if ctx.wants_keyboard_input() || self.visible.is_empty() {
+            return None;
+        }
+
+        let (up, down, space) = ctx.input(|i| {
+            (
+                i.key_pressed(Key::ArrowUp),
+                i.key_pressed(Key::ArrowDown),
+                i.key_pressed(Key::Space),
+            )
+        });
+
+        if up || down {
+            let position = self
+                .focused
+                .as_ref()
+                .and_then(|focused| self.visible.iter().position(|file| file == focused));
+            let next = match position {
+                Some(idx) if up => idx.saturating_sub(1),
+                Some(idx) => (idx + 1).min(self.visible.len() - 1),
+                None if up => self.visible.len() - 1,
+                None => 0,
+            };
+            self.focused = Some(self.visible[next].clone());
+            self.anchor = self.focused.clone();
+            self.scroll_to_focused = true;
+        }
+
+        if space && ctx.memory(|memory| memory.focused().is_none()) {
+            self.focused
+                .clone()
+                .filter(|focused| self.visible.contains(focused))
+        } else {
+            None
+        }
+    }
+
+    /// Applies keyboard `toggled` file and `bulk_action` to the points `state`.
+    ///
+    /// Missing points are inserted as `empty`, `opened` gives access to the point selection flag.
+    /// Set meta files are skipped by [BulkAction::Invert].
+    ///
+    /// Returns true if selection was changed.
+    pub fn apply<T: Clone>(
+        &self,
+        state: &mut BTreeMap<String, T>,
+        toggled: Option<String>,
+        bulk_action: Option<BulkAction>,
+        select_single: bool,
+        empty: T,
+        opened: impl Fn(&mut T) -> &mut bool,
+    ) -> bool {
+        let mut changed = false;
+
+        if let Some(point) = toggled {
+            let cache = state.entry(point.clone()).or_insert_with(|| empty.clone());
+            let point_opened = opened(cache);
+            *point_opened = !*point_opened;
+            if *point_opened && select_single {
+                for (key, cache) in state.iter_mut() {
+                    if key != &point {
+                        *opened(cache) = false;
+                    }
+                }
+            }
+            changed = true;
+        }
+
+        match bulk_action {
+            Some(BulkAction::Invert) => {
+                for key in self.visible.iter().filter(|key| !is_set_meta(key)) {
+                    let cache = state.entry(key.clone()).or_insert_with(|| empty.clone());
+                    let point_opened = opened(cache);
+                    *point_opened = !*point_opened;
+                }
+                changed = true;
+            }
+            Some(BulkAction::SelectNone) => {
+                for cache in state.values_mut() {
+                    *opened(cache) = false;
+                }
+                changed = true;
+            }
+            None => {}
+        }
+
+        changed
+    }
+
+    /// Draws bulk selection buttons.
+    pub fn input(&self, ui: &mut Ui) -> Option<BulkAction> {
+        let mut action = None;
+        if ui
+            .button("invert")
+            .on_hover_text(
+                "Инвертировать выделение видимых файлов (↑↓ - навигация, пробел - выделить)",
+            )
+            .clicked()
+        {
+            action = Some(BulkAction::Invert);
+        }
+        if ui
+            .button("none")
+            .on_hover_text("Снять выделение со всех файлов")
+            .clicked()
+        {
+            action = Some(BulkAction::SelectNone);
+        }
+        action
+    }
+}
@@ -0,0 +1,351 @@
+//! Job queue of the [PointProcessor] web workers (web only).
+//!
+//! Jobs are dispatched in FIFO order to the least busy worker with at most
+//! [WorkerPool::max_in_flight] jobs per worker. Waiting jobs are woken when a worker slot is freed.
+//!
+//! Oneshot bridges do not report worker crashes, so every worker instance gets a token (sent with
+//! each job) and reports it to the [LOST_CHANNEL] broadcast channel from its `error` event handler
+//! (see [watch_worker_errors]). Jobs of a lost worker are requeued and the worker is respawned.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    future::{poll_fn, Future},
+    path::PathBuf,
+    pin::pin,
+    sync::Arc,
+    task::{Poll, Waker},
+};
+
+use egui::{mutex::Mutex, Ui};
+use gloo::worker::{oneshot::OneshotBridge, Spawnable};
+use processing::{
+    histogram::HistogramParams, postprocess::PostProcessParams, process::ProcessParams,
+    viewer::PointState,
+};
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{BroadcastChannel, MessageEvent, WorkerGlobalScope};
+
+use crate::{
+    event_filter::EventFilter,
+    events_cache::{CachedEvents, ChannelCounts},
+    time_window::TimeWindow,
+    PointProcessor,
+};
+
+/// Default limit of simultaneous jobs of a worker.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 2;
+/// How many times a lost job is requeued.
+const MAX_RETRIES: usize = 1;
+/// Broadcast channel workers report their token to when they crash.
+pub const LOST_CHANNEL: &str = "point-processor-lost";
+/// Global of the worker scope holding token of the worker (read by the `error` handler).
+const TOKEN_GLOBAL: &str = "__point_processor_token";
+
+pub type ProcessorArgs = (
+    PathBuf,
+    ProcessParams,
+    PostProcessParams,
+    TimeWindow,
+    HistogramParams,
+    EventFilter,
+    // keep events
+    bool,
+);
+/// Point state, unfiltered channel counts and events (if requested).
+pub type ProcessorOutput = Option<(PointState, ChannelCounts, Option<CachedEvents>)>;
+
+fn spawn_worker() -> OneshotBridge<PointProcessor> {
+    PointProcessor::spawner().spawn("./worker.js")
+}
+
+/// Registers `error` handler of the worker scope reporting worker token to [LOST_CHANNEL].
+///
+/// Must be called once in the worker before the registrar. Handler is plain JS, since the wasm
+/// instance can be broken at the moment of the error.
+pub fn watch_worker_errors() {
+    let handler = js_sys::Function::new_with_args(
+        "event",
+        &format!("new BroadcastChannel('{LOST_CHANNEL}').postMessage(self.{TOKEN_GLOBAL});"),
+    );
+    js_sys::global()
+        .unchecked_into::<WorkerGlobalScope>()
+        .add_event_listener_with_callback("error", &handler)
+        .unwrap();
+}
+
+/// Remembers `token` of the current worker (see [watch_worker_errors]).
+pub fn set_worker_token(token: u64) {
+    js_sys::Reflect::set(
+        &js_sys::global(),
+        &JsValue::from_str(TOKEN_GLOBAL),
+        &JsValue::from_f64(token as f64),
+    )
+    .unwrap();
+}
+
+struct WorkerSlot {
+    bridge: OneshotBridge<PointProcessor>,
+    /// Token of the current worker instance (changed on every respawn).
+    token: u64,
+    /// Wakers of the running jobs (by job id), woken if the worker is lost.
+    running: BTreeMap<u64, Waker>,
+    in_flight: usize,
+    completed: usize,
+    respawns: usize,
+}
+
+/// Pool of the [PointProcessor] workers.
+pub struct WorkerPool {
+    workers: Vec<WorkerSlot>,
+    /// Ids of the jobs waiting for a worker (in dispatch order).
+    queue: VecDeque<u64>,
+    /// Wakers of the waiting jobs.
+    waiting: BTreeMap<u64, Waker>,
+    next_job: u64,
+    next_token: u64,
+    /// Limit of simultaneous jobs of a worker.
+    pub max_in_flight: usize,
+}
+
+impl WorkerPool {
+    /// Spawns `concurrency` workers (at least one).
+    pub fn new(concurrency: usize) -> Self {
+        let concurrency = concurrency.max(1);
+        Self {
+            workers: (0..concurrency)
+                .map(|token| WorkerSlot {
+                    bridge: spawn_worker(),
+                    token: token as u64,
+                    running: BTreeMap::new(),
+                    in_flight: 0,
+                    completed: 0,
+                    respawns: 0,
+                })
+                .collect(),
+            queue: VecDeque::new(),
+            waiting: BTreeMap::new(),
+            next_job: 0,
+            next_token: concurrency as u64,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+        }
+    }
+
+    /// Creates shared pool listening to the [LOST_CHANNEL] (see [WorkerPool::new]).
+    pub fn shared(concurrency: usize) -> Arc<Mutex<Self>> {
+        let pool = Arc::new(Mutex::new(Self::new(concurrency)));
+
+        let channel = BroadcastChannel::new(LOST_CHANNEL).unwrap();
+        let on_message = {
+            let pool = Arc::clone(&pool);
+            Closure::<dyn Fn(MessageEvent)>::new(move |event: MessageEvent| {
+                if let Some(token) = event.data().as_f64() {
+                    pool.lock().lost(token as u64);
+                }
+            })
+        };
+        channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        // pool lives as long as the app
+        on_message.forget();
+        std::mem::forget(channel);
+
+        pool
+    }
+
+    /// Number of the jobs waiting for a worker.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Adds a job to the queue (requeued jobs are dispatched first).
+    fn enqueue(&mut self, requeued: bool) -> u64 {
+        let job = self.next_job;
+        self.next_job += 1;
+        if requeued {
+            self.queue.push_front(job);
+        } else {
+            self.queue.push_back(job);
+        }
+        job
+    }
+
+    /// Wakes the first waiting job (if there is a free slot it will be dispatched).
+    fn wake_front(&mut self) {
+        if let Some(waker) = self.queue.front().and_then(|job| self.waiting.remove(job)) {
+            waker.wake();
+        }
+    }
+
+    /// Dispatches `job` to the least busy worker if it is the first in the queue and
+    /// there is a worker with a free slot.
+    ///
+    /// Returns worker index, its token and bridge to run the job on.
+    fn try_dispatch(&mut self, job: u64) -> Option<(usize, u64, OneshotBridge<PointProcessor>)> {
+        if self.queue.front() != Some(&job) {
+            return None;
+        }
+        let max_in_flight = self.max_in_flight.max(1);
+        let (idx, worker) = self
+            .workers
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, worker)| worker.in_flight < max_in_flight)
+            .min_by_key(|(_, worker)| worker.in_flight)?;
+
+        worker.in_flight += 1;
+        let dispatched = (idx, worker.token, worker.bridge.fork());
+
+        self.queue.pop_front();
+        self.waiting.remove(&job);
+        self.wake_front();
+        Some(dispatched)
+    }
+
+    /// Worker instance with `token` (None if it was respawned).
+    fn worker(&mut self, idx: usize, token: u64) -> Option<&mut WorkerSlot> {
+        Some(&mut self.workers[idx]).filter(|worker| worker.token == token)
+    }
+
+    fn finish(&mut self, idx: usize, token: u64, job: u64) {
+        if let Some(worker) = self.worker(idx, token) {
+            worker.running.remove(&job);
+            worker.in_flight -= 1;
+            worker.completed += 1;
+            self.wake_front();
+        }
+    }
+
+    /// Respawns worker instance with `token` and wakes its jobs (to be requeued).
+    fn lost(&mut self, token: u64) {
+        let next_token = self.next_token;
+        let Some((idx, worker)) = self
+            .workers
+            .iter_mut()
+            .enumerate()
+            .find(|(_, worker)| worker.token == token)
+        else {
+            return;
+        };
+
+        tracing::warn!("worker {idx} crashed, respawning");
+        worker.bridge = spawn_worker();
+        worker.token = next_token;
+        worker.in_flight = 0;
+        worker.respawns += 1;
+        for waker in std::mem::take(&mut worker.running).into_values() {
+            waker.wake();
+        }
+        self.next_token += 1;
+        self.wake_front();
+    }
+
+    /// Draws workers activity.
+    pub fn input(&mut self, ui: &mut Ui) {
+        let active = self
+            .workers
+            .iter()
+            .map(|worker| worker.in_flight)
+            .sum::<usize>();
+        egui::CollapsingHeader::new(format!(
+            "workers: {active} running, {} queued",
+            self.queue.len()
+        ))
+        .id_salt("worker_pool")
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("jobs per worker:");
+                if ui
+                    .add(egui::DragValue::new(&mut self.max_in_flight).range(1..=8))
+                    .on_hover_text("Максимальное количество одновременных задач одного воркера")
+                    .changed()
+                {
+                    self.wake_front();
+                }
+            });
+            for (idx, worker) in self.workers.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::ProgressBar::new(
+                            worker.in_flight as f32 / self.max_in_flight.max(1) as f32,
+                        )
+                        .desired_width(60.0),
+                    );
+                    ui.label(format!(
+                        "#{idx}: {} running, {} done",
+                        worker.in_flight, worker.completed
+                    ));
+                    if worker.respawns != 0 {
+                        ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!("{} restarts", worker.respawns),
+                        )
+                        .on_hover_text("Воркер перезапущен после сбоя");
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Processes point on the least busy worker of the `pool`.
+///
+/// Returns None if the point can not be processed or its worker crashed more than [MAX_RETRIES] times.
+pub async fn run(pool: Arc<Mutex<WorkerPool>>, args: ProcessorArgs) -> ProcessorOutput {
+    run_notify(pool, args, || {}).await
+}
+
+/// Same as [run], `on_dispatch` is called every time the job is sent to a worker.
+pub async fn run_notify(
+    pool: Arc<Mutex<WorkerPool>>,
+    args: ProcessorArgs,
+    on_dispatch: impl Fn(),
+) -> ProcessorOutput {
+    let mut job = pool.lock().enqueue(false);
+    let mut retries = 0;
+
+    loop {
+        let (idx, token, mut bridge) = poll_fn(|cx| {
+            let mut pool = pool.lock();
+            match pool.try_dispatch(job) {
+                Some(dispatched) => Poll::Ready(dispatched),
+                None => {
+                    pool.waiting.insert(job, cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await;
+        on_dispatch();
+
+        let output = {
+            let mut run = pin!(bridge.run((token, args.clone())));
+            poll_fn(|cx| {
+                if let Poll::Ready(output) = run.as_mut().poll(cx) {
+                    return Poll::Ready(Some(output));
+                }
+                match pool.lock().worker(idx, token) {
+                    Some(worker) => {
+                        worker.running.insert(job, cx.waker().clone());
+                        Poll::Pending
+                    }
+                    None => Poll::Ready(None),
+                }
+            })
+            .await
+        };
+
+        let mut pool = pool.lock();
+        match output {
+            Some(output) => {
+                pool.finish(idx, token, job);
+                return output;
+            }
+            None => {
+                if retries == MAX_RETRIES {
+                    tracing::error!("job {:?} was lost {} times", args.0, retries + 1);
+                    return None;
+                }
+                retries += 1;
+                job = pool.enqueue(true);
+            }
+        }
+    }
+}